@@ -1,10 +1,309 @@
+mod report;
+
+use std::collections::HashMap;
 use std::env;
 use std::fs;
+use std::io::{self, BufRead, BufReader};
+use std::ops::RangeInclusive;
+
+use report::ReportFormat;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Rounding {
+    Floor,
+    Ceil,
+}
+
+/// Fuel needed for a mass is `mass / divisor`, rounded, minus `subtraction`,
+/// and never less than `minimum_fuel`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct FuelModel {
+    divisor: u64,
+    subtraction: u64,
+    rounding: Rounding,
+    minimum_fuel: u64,
+}
+
+impl Default for FuelModel {
+    fn default() -> Self {
+        FuelModel {
+            divisor: 3,
+            subtraction: 2,
+            rounding: Rounding::Floor,
+            minimum_fuel: 0,
+        }
+    }
+}
+
+impl FuelModel {
+    fn new(
+        divisor: u64,
+        subtraction: u64,
+        rounding: Rounding,
+        minimum_fuel: u64,
+    ) -> Result<FuelModel, &'static str> {
+        if divisor == 0 {
+            return Err("Divisor must be positive");
+        }
+        Ok(FuelModel {
+            divisor,
+            subtraction,
+            rounding,
+            minimum_fuel,
+        })
+    }
+
+    fn fuel_for_mass(&self, mass: u64) -> u64 {
+        let divided = match self.rounding {
+            Rounding::Floor => mass / self.divisor,
+            Rounding::Ceil => mass.div_ceil(self.divisor),
+        };
+        divided
+            .saturating_sub(self.subtraction)
+            .max(self.minimum_fuel)
+    }
+
+    /// Each extra amount of fuel needed to carry the previous amount, stopping
+    /// once adding more fuel no longer shrinks the requirement.
+    fn fuel_chain(&self, fuel: u64) -> FuelChain {
+        FuelChain {
+            model: *self,
+            last_fuel_weight: fuel,
+        }
+    }
+
+    /// Sum of the fuel chain, `None` if it overflows.
+    fn fuel_for_fuel(&self, fuel: u64) -> Option<u64> {
+        self.fuel_chain(fuel)
+            .try_fold(0u64, |total, next| total.checked_add(next))
+    }
+
+    /// Fuel for a module including the fuel for its fuel, `None` on overflow.
+    fn module_total_fuel(&self, mass: u64) -> Option<u64> {
+        let base_fuel = self.fuel_for_mass(mass);
+        base_fuel.checked_add(self.fuel_for_fuel(base_fuel)?)
+    }
+
+    /// Every mass whose base fuel is exactly `fuel`, `None` if no mass needs
+    /// that amount.
+    fn masses_for_fuel(&self, fuel: u64) -> Option<RangeInclusive<u64>> {
+        masses_with_value(fuel, |mass| Some(self.fuel_for_mass(mass)))
+    }
+
+    /// Every mass whose total fuel, fuel for fuel included, is exactly `fuel`.
+    fn masses_for_total_fuel(&self, fuel: u64) -> Option<RangeInclusive<u64>> {
+        masses_with_value(fuel, |mass| self.module_total_fuel(mass))
+    }
+
+    /// Largest module mass whose total fuel fits within `budget`, `None` if
+    /// even a mass of 0 needs more.
+    fn max_mass_for_budget(&self, budget: u64) -> Option<u64> {
+        match first_mass_where(|mass| self.module_total_fuel(mass).is_none_or(|x| x > budget)) {
+            Some(0) => None,
+            Some(mass) => Some(mass - 1),
+            None => Some(u64::MAX),
+        }
+    }
+}
+
+/// Smallest mass for which `predicate` holds, assuming that once it holds for
+/// a mass it holds for every larger mass too.
+fn first_mass_where(predicate: impl Fn(u64) -> bool) -> Option<u64> {
+    if !predicate(u64::MAX) {
+        return None;
+    }
+    let mut low = 0;
+    let mut high = u64::MAX;
+    while low < high {
+        let middle = low + (high - low) / 2;
+        if predicate(middle) {
+            high = middle;
+        } else {
+            low = middle + 1;
+        }
+    }
+    Some(low)
+}
+
+/// Range of masses mapping to exactly `value` under a non-decreasing fuel
+/// function, where `None` stands for an overflowing amount.
+fn masses_with_value(value: u64, fuel: impl Fn(u64) -> Option<u64>) -> Option<RangeInclusive<u64>> {
+    let low = first_mass_where(|mass| fuel(mass).is_none_or(|x| x >= value))?;
+    if fuel(low) != Some(value) {
+        return None;
+    }
+    let high = match first_mass_where(|mass| fuel(mass).is_none_or(|x| x > value)) {
+        Some(mass) => mass - 1,
+        None => u64::MAX,
+    };
+    Some(low..=high)
+}
+
+struct FuelChain {
+    model: FuelModel,
+    last_fuel_weight: u64,
+}
+
+impl Iterator for FuelChain {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        let next = self.model.fuel_for_mass(self.last_fuel_weight);
+        if next == 0 || next >= self.last_fuel_weight {
+            return None;
+        }
+        self.last_fuel_weight = next;
+        Some(next)
+    }
+}
+
+/// Remembers the fuel for fuel of every amount seen, so batches with many
+/// masses only walk each shared tail of a fuel chain once.
+///
+/// Only amounts up to `limit` are remembered, which bounds the memory used
+/// while still covering the tails that every long chain ends in.
+struct FuelCache {
+    model: FuelModel,
+    limit: u64,
+    known: HashMap<u64, u64>,
+}
+
+impl FuelCache {
+    #[cfg(test)]
+    fn new(model: FuelModel) -> FuelCache {
+        FuelCache::with_limit(model, u64::MAX)
+    }
+
+    fn with_limit(model: FuelModel, limit: u64) -> FuelCache {
+        FuelCache {
+            model,
+            limit,
+            known: HashMap::new(),
+        }
+    }
+
+    fn fuel_for_fuel(&mut self, fuel: u64) -> Option<u64> {
+        let mut path: Vec<u64> = vec![];
+        let mut total: u64 = 0;
+        let mut below: Option<u64> = None;
+        let mut current = fuel;
+        loop {
+            if let Some(&known) = self.known.get(&current) {
+                total = known;
+                below = Some(current);
+                break;
+            }
+            path.push(current);
+            let next = self.model.fuel_for_mass(current);
+            if next == 0 || next >= current {
+                break;
+            }
+            current = next;
+        }
+
+        // Walk back up the chain, each amount needs the fuel below it plus
+        // whatever that fuel needs in turn.
+        for &amount in path.iter().rev() {
+            if let Some(next) = below {
+                total = total.checked_add(next)?;
+            }
+            if amount <= self.limit {
+                self.known.insert(amount, total);
+            }
+            below = Some(amount);
+        }
+        Some(total)
+    }
+}
+
+/// Running totals over a manifest, `None` from `add` if a total overflows.
+#[derive(Debug, Default, PartialEq)]
+struct FuelTotals {
+    modules: usize,
+    fuel: u64,
+    fuel_for_fuel: u64,
+}
+
+impl FuelTotals {
+    fn add(&mut self, cache: &mut FuelCache, mass: u64) -> Option<()> {
+        let fuel = cache.model.fuel_for_mass(mass);
+        self.fuel = self.fuel.checked_add(fuel)?;
+        self.fuel_for_fuel = self.fuel_for_fuel.checked_add(cache.fuel_for_fuel(fuel)?)?;
+        self.modules += 1;
+        Some(())
+    }
+
+    fn grand_total(&self) -> Option<u64> {
+        self.fuel.checked_add(self.fuel_for_fuel)
+    }
+}
+
+/// Largest amount the streaming cache remembers, keeping its size fixed no
+/// matter how many modules a manifest lists.
+const STREAM_CACHE_LIMIT: u64 = 1 << 16;
+
+/// Sums a manifest one line at a time, so memory use does not grow with the
+/// number of modules. Blank lines are skipped.
+fn read_totals<R: BufRead>(mut reader: R, model: FuelModel) -> Result<FuelTotals, String> {
+    let mut cache = FuelCache::with_limit(model, STREAM_CACHE_LIMIT);
+    let mut totals = FuelTotals::default();
+    let mut line = String::new();
+    let mut n = 0;
+    loop {
+        line.clear();
+        n += 1;
+        let read = reader
+            .read_line(&mut line)
+            .map_err(|error| format!("line {}: {}", n, error))?;
+        if read == 0 {
+            return Ok(totals);
+        }
+        if let Some(mass) = parse_mass(n, &line)? {
+            totals
+                .add(&mut cache, mass)
+                .ok_or_else(|| format!("line {}: total fuel overflowed", n))?;
+        }
+    }
+}
+
+/// Mass on line `n` of a manifest, `None` for a blank line.
+fn parse_mass(n: usize, line: &str) -> Result<Option<u64>, String> {
+    let mass = line.trim();
+    if mass.is_empty() {
+        return Ok(None);
+    }
+    mass.parse()
+        .map(Some)
+        .map_err(|_| format!("line {}: `{}` is not a mass", n, mass))
+}
+
+/// Every mass in a manifest, for the report which needs them all at once.
+fn read_masses<R: BufRead>(reader: R) -> Result<Vec<u64>, String> {
+    let mut masses = vec![];
+    for (n, line) in reader.lines().enumerate() {
+        let line = line.map_err(|error| format!("line {}: {}", n + 1, error))?;
+        masses.extend(parse_mass(n + 1, &line)?);
+    }
+    Ok(masses)
+}
+
+/// The manifest file, or stdin when it is `-`.
+fn open_manifest(filename: &str) -> Box<dyn BufRead> {
+    if filename == "-" {
+        Box::new(io::stdin().lock())
+    } else {
+        let file = fs::File::open(filename).expect("Could not open input file");
+        Box::new(BufReader::new(file))
+    }
+}
 
+/// Original puzzle formula on `i32`, kept as the reference for `FuelModel`.
+#[cfg(test)]
 fn get_fuel_amount(weight: i32) -> i32 {
     weight / 3 - 2
 }
 
+#[cfg(test)]
 fn get_fuel_for_fuel(fuel: i32) -> i32 {
     let mut last_fuel_weight = fuel;
     let mut fuel_of_fuel_weights: Vec<i32> = vec![];
@@ -17,31 +316,92 @@ fn get_fuel_for_fuel(fuel: i32) -> i32 {
     fuel_of_fuel_weights.iter().sum()
 }
 
+fn parse_model(args: &[String]) -> Result<FuelModel, &'static str> {
+    let default = FuelModel::default();
+    let mut divisor = default.divisor;
+    let mut subtraction = default.subtraction;
+    let mut rounding = default.rounding;
+    let mut minimum_fuel = default.minimum_fuel;
+
+    let mut options = args.iter();
+    while let Some(option) = options.next() {
+        let mut value = || -> Result<u64, &'static str> {
+            options
+                .next()
+                .and_then(|x| x.parse().ok())
+                .ok_or("Expected a number after option")
+        };
+        match option.as_str() {
+            "--divisor" => divisor = value()?,
+            "--subtract" => subtraction = value()?,
+            "--minimum" => minimum_fuel = value()?,
+            "--ceil" => rounding = Rounding::Ceil,
+            _ => return Err("Unknown option"),
+        }
+    }
+
+    FuelModel::new(divisor, subtraction, rounding, minimum_fuel)
+}
+
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
+    let mut args: Vec<String> = env::args().collect();
+    if args.len() < 2 {
         panic!("No input file supplied!");
     }
+    if args[1] == "inverse" || args[1] == "budget" {
+        let fuel: u64 = args
+            .get(2)
+            .and_then(|x| x.parse().ok())
+            .expect("No fuel amount supplied!");
+        let model = parse_model(&args[3..]).unwrap();
+        if args[1] == "budget" {
+            match model.max_mass_for_budget(fuel) {
+                Some(mass) => println!("Largest module mass for a budget of {}: {}", fuel, mass),
+                None => println!("No module mass fits a budget of {}", fuel),
+            }
+            return;
+        }
+        match model.masses_for_fuel(fuel) {
+            Some(masses) => println!("Masses needing {} fuel: {:?}", fuel, masses),
+            None => println!("No mass needs exactly {} fuel", fuel),
+        }
+        match model.masses_for_total_fuel(fuel) {
+            Some(masses) => println!("Masses needing {} fuel in total: {:?}", fuel, masses),
+            None => println!("No mass needs exactly {} fuel in total", fuel),
+        }
+        return;
+    }
+    let report_format = match args.iter().position(|x| x == "--report") {
+        Some(pos) if pos + 1 < args.len() => {
+            let format = ReportFormat::from_str(&args[pos + 1]).expect("Unknown report format");
+            args.drain(pos..pos + 2);
+            Some(format)
+        }
+        Some(_) => panic!("No report format supplied!"),
+        None => None,
+    };
     let filename = &args[1];
-    println!("input file is: {}", filename);
+    let model = parse_model(&args[2..]).unwrap();
 
-    let file_content = fs::read_to_string(filename).expect("Could not open input file");
-
-    let mut fuel_amounts: Vec<i32> = vec![];
-
-    for n in file_content.split('\n') {
-        let weight = i32::from_str_radix(n, 10).unwrap();
-        fuel_amounts.push(get_fuel_amount(weight));
-        get_fuel_for_fuel(get_fuel_amount(weight));
+    if let Some(format) = report_format {
+        let masses =
+            read_masses(open_manifest(filename)).unwrap_or_else(|error| panic!("{}", error));
+        let modules = report::build_report(&model, &masses).expect("Module fuel overflowed");
+        print!("{}", format.render(&modules));
+        return;
     }
 
-    let total_fuel: i32 = fuel_amounts.iter().sum();
+    println!("input file is: {}", filename);
 
-    let total_fuel_for_fuel: i32 = fuel_amounts.iter().map(|&x| get_fuel_for_fuel(x)).sum();
+    let totals =
+        read_totals(open_manifest(filename), model).unwrap_or_else(|error| panic!("{}", error));
 
-    println!("Total fuel required: {}", total_fuel);
-    println!("Total fuel for fuel: {}", total_fuel_for_fuel);
-    println!("Gran total: {}", total_fuel + total_fuel_for_fuel);
+    println!("Total fuel required: {}", totals.fuel);
+    println!("Total fuel for fuel: {}", totals.fuel_for_fuel);
+    println!(
+        "Gran total: {}",
+        totals.grand_total().expect("Gran total overflowed")
+    );
 }
 
 #[test]
@@ -54,3 +414,168 @@ fn test_fuel_amount() {
 fn test_fuel_of_fuel() {
     assert_eq!(get_fuel_for_fuel(654), 966 - 654);
 }
+
+#[test]
+fn test_default_model_matches_puzzle() {
+    let model = FuelModel::default();
+    for &mass in &[12, 14, 1969, 100756] {
+        let fuel = get_fuel_amount(mass);
+        assert_eq!(fuel as u64, model.fuel_for_mass(mass as u64));
+        assert_eq!(
+            Some(get_fuel_for_fuel(fuel) as u64),
+            model.fuel_for_fuel(fuel as u64)
+        );
+    }
+    assert_eq!(0, model.fuel_for_mass(5));
+}
+
+#[test]
+fn test_fuel_chain() {
+    let chain: Vec<u64> = FuelModel::default().fuel_chain(654).collect();
+    assert_eq!(vec![216, 70, 21, 5], chain);
+}
+
+#[test]
+fn test_custom_model() {
+    let model = FuelModel::new(4, 1, Rounding::Ceil, 2).unwrap();
+    assert_eq!(2, model.fuel_for_mass(3));
+    assert_eq!(24, model.fuel_for_mass(100));
+    assert_eq!(Some(5 + 2), model.fuel_for_fuel(24));
+    assert_eq!(
+        Err("Divisor must be positive"),
+        FuelModel::new(0, 2, Rounding::Floor, 0).map(|_| ())
+    );
+}
+
+#[test]
+fn test_total_overflow() {
+    let manifest = format!("{}\n", u64::MAX / 2).repeat(4);
+    assert!(read_totals(manifest.as_bytes(), FuelModel::default()).is_ok());
+    assert_eq!(
+        Err("line 3: total fuel overflowed".to_string()),
+        read_totals(
+            manifest.as_bytes(),
+            FuelModel::new(1, 0, Rounding::Floor, 0).unwrap()
+        )
+    );
+}
+
+#[test]
+fn test_read_totals() {
+    let totals = read_totals("12\n14\r\n\n1969\n100756".as_bytes(), FuelModel::default());
+    assert_eq!(
+        Ok(FuelTotals {
+            modules: 4,
+            fuel: 2 + 2 + 654 + 33583,
+            fuel_for_fuel: 966 - 654 + 50346 - 33583,
+        }),
+        totals
+    );
+    assert_eq!(
+        Err("line 2: `fourteen` is not a mass".to_string()),
+        read_totals("12\nfourteen\n".as_bytes(), FuelModel::default())
+    );
+}
+
+#[test]
+fn test_read_masses() {
+    assert_eq!(
+        Ok(vec![12, 14, 1969]),
+        read_masses("12\n 14\r\n\n1969\n".as_bytes())
+    );
+    assert_eq!(
+        Err("line 3: `-5` is not a mass".to_string()),
+        read_masses("12\n\n-5\n".as_bytes())
+    );
+}
+
+/// Small xorshift generator so the property tests below are reproducible.
+#[cfg(test)]
+fn random_masses(seed: u64, count: usize, max: u64) -> Vec<u64> {
+    let mut state = seed;
+    (0..count)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state % max
+        })
+        .collect()
+}
+
+#[test]
+fn test_fuel_for_fuel_matches_reference() {
+    let model = FuelModel::default();
+    let mut cache = FuelCache::new(model);
+    for mass in random_masses(0x2545_f491_4f6c_dd1d, 10_000, i32::MAX as u64) {
+        let expected = get_fuel_for_fuel(get_fuel_amount(mass as i32)) as u64;
+        let fuel = model.fuel_for_mass(mass);
+        assert_eq!(Some(expected), model.fuel_for_fuel(fuel), "mass {}", mass);
+        assert_eq!(Some(expected), cache.fuel_for_fuel(fuel), "mass {}", mass);
+    }
+}
+
+#[test]
+fn test_fuel_cache_batch() {
+    let model = FuelModel::new(2, 1, Rounding::Ceil, 0).unwrap();
+    let mut cache = FuelCache::new(model);
+    for _ in 0..2 {
+        for fuel in random_masses(42, 1_000, 1_000_000) {
+            assert_eq!(model.fuel_for_fuel(fuel), cache.fuel_for_fuel(fuel));
+        }
+    }
+
+    let masses = random_masses(7, 1_000, 1_000_000);
+    let expected_total: u64 = masses
+        .iter()
+        .map(|&mass| model.fuel_for_fuel(model.fuel_for_mass(mass)).unwrap())
+        .sum();
+    let mut totals = FuelTotals::default();
+    let mut limited = FuelCache::with_limit(model, 1_000);
+    for &mass in &masses {
+        totals.add(&mut limited, mass).unwrap();
+    }
+    assert_eq!(expected_total, totals.fuel_for_fuel);
+    assert!(limited.known.keys().all(|&amount| amount <= 1_000));
+}
+
+#[test]
+fn test_masses_for_fuel() {
+    let model = FuelModel::default();
+    assert_eq!(Some(1968..=1970), model.masses_for_fuel(654));
+    assert_eq!(Some(0..=8), model.masses_for_fuel(0));
+    assert_eq!(Some(1968..=1970), model.masses_for_total_fuel(966));
+    assert_eq!(None, model.masses_for_total_fuel(9));
+    assert_eq!(
+        Some(u64::MAX..=u64::MAX),
+        model.masses_for_fuel(u64::MAX / 3 - 2)
+    );
+}
+
+#[test]
+fn test_inverse_matches_brute_force() {
+    let model = FuelModel::new(4, 1, Rounding::Ceil, 1).unwrap();
+    for fuel in 0..200 {
+        let masses: Vec<u64> = (0..2000)
+            .filter(|&mass| model.module_total_fuel(mass) == Some(fuel))
+            .collect();
+        let expected = match (masses.first(), masses.last()) {
+            (Some(&low), Some(&high)) => Some(low..=high),
+            _ => None,
+        };
+        assert_eq!(expected, model.masses_for_total_fuel(fuel), "fuel {}", fuel);
+    }
+}
+
+#[test]
+fn test_max_mass_for_budget() {
+    let model = FuelModel::default();
+    assert_eq!(Some(1970), model.max_mass_for_budget(966));
+    assert_eq!(Some(1967), model.max_mass_for_budget(965));
+    assert_eq!(Some(8), model.max_mass_for_budget(0));
+
+    let model = FuelModel::new(3, 2, Rounding::Floor, 2).unwrap();
+    assert_eq!(None, model.max_mass_for_budget(0));
+    assert_eq!(None, model.max_mass_for_budget(1));
+    assert_eq!(Some(14), model.max_mass_for_budget(2));
+}
@@ -0,0 +1,136 @@
+use crate::FuelModel;
+
+#[derive(Debug, PartialEq)]
+pub struct ModuleReport {
+    pub mass: u64,
+    pub base_fuel: u64,
+    pub fuel_chain: Vec<u64>,
+    pub total: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReportFormat {
+    Text,
+    Csv,
+    Json,
+}
+
+/// Fuel breakdown for every module, `None` if a module total overflows.
+pub fn build_report(model: &FuelModel, masses: &[u64]) -> Option<Vec<ModuleReport>> {
+    masses
+        .iter()
+        .map(|&mass| {
+            let base_fuel = model.fuel_for_mass(mass);
+            let fuel_chain: Vec<u64> = model.fuel_chain(base_fuel).collect();
+            let total = model.module_total_fuel(mass)?;
+            Some(ModuleReport {
+                mass,
+                base_fuel,
+                fuel_chain,
+                total,
+            })
+        })
+        .collect()
+}
+
+fn join(values: &[u64], separator: &str) -> String {
+    let values: Vec<String> = values.iter().map(|x| x.to_string()).collect();
+    values.join(separator)
+}
+
+impl ReportFormat {
+    pub fn from_str(x: &str) -> Option<ReportFormat> {
+        match x {
+            "text" => Some(ReportFormat::Text),
+            "csv" => Some(ReportFormat::Csv),
+            "json" => Some(ReportFormat::Json),
+            _ => None,
+        }
+    }
+
+    pub fn render(self, modules: &[ModuleReport]) -> String {
+        match self {
+            ReportFormat::Text => to_text(modules),
+            ReportFormat::Csv => to_csv(modules),
+            ReportFormat::Json => to_json(modules),
+        }
+    }
+}
+
+fn to_text(modules: &[ModuleReport]) -> String {
+    let mut output = String::new();
+    for (n, module) in modules.iter().enumerate() {
+        output += &format!(
+            "Module {}: mass {}, base fuel {}, fuel for fuel [{}], total {}\n",
+            n + 1,
+            module.mass,
+            module.base_fuel,
+            join(&module.fuel_chain, ", "),
+            module.total
+        );
+    }
+    output
+}
+
+/// One row per module, with the fuel chain as a `;` separated field.
+fn to_csv(modules: &[ModuleReport]) -> String {
+    let mut output = String::from("module,mass,base_fuel,fuel_chain,total\n");
+    for (n, module) in modules.iter().enumerate() {
+        output += &format!(
+            "{},{},{},{},{}\n",
+            n + 1,
+            module.mass,
+            module.base_fuel,
+            join(&module.fuel_chain, ";"),
+            module.total
+        );
+    }
+    output
+}
+
+fn to_json(modules: &[ModuleReport]) -> String {
+    let rows: Vec<String> = modules
+        .iter()
+        .enumerate()
+        .map(|(n, module)| {
+            format!(
+                "  {{\"module\": {}, \"mass\": {}, \"base_fuel\": {}, \"fuel_chain\": [{}], \"total\": {}}}",
+                n + 1,
+                module.mass,
+                module.base_fuel,
+                join(&module.fuel_chain, ", "),
+                module.total
+            )
+        })
+        .collect();
+    format!("[\n{}\n]\n", rows.join(",\n"))
+}
+
+#[test]
+fn test_build_report() {
+    let modules = build_report(&FuelModel::default(), &[14, 1969]).unwrap();
+    assert_eq!(
+        ModuleReport {
+            mass: 14,
+            base_fuel: 2,
+            fuel_chain: vec![],
+            total: 2,
+        },
+        modules[0]
+    );
+    assert_eq!(vec![216, 70, 21, 5], modules[1].fuel_chain);
+    assert_eq!(966, modules[1].total);
+}
+
+#[test]
+fn test_export_formats() {
+    let modules = build_report(&FuelModel::default(), &[14, 1969]).unwrap();
+    assert_eq!(
+        "module,mass,base_fuel,fuel_chain,total\n1,14,2,,2\n2,1969,654,216;70;21;5,966\n",
+        ReportFormat::Csv.render(&modules)
+    );
+    assert_eq!(
+        "[\n  {\"module\": 1, \"mass\": 14, \"base_fuel\": 2, \"fuel_chain\": [], \"total\": 2},\n  {\"module\": 2, \"mass\": 1969, \"base_fuel\": 654, \"fuel_chain\": [216, 70, 21, 5], \"total\": 966}\n]\n",
+        ReportFormat::Json.render(&modules)
+    );
+}
@@ -1,24 +1,41 @@
 use std::env;
-use std::fs;
 
-fn get_mutated_sequence(v: &mut Vec<i32>) -> &mut Vec<i32> {
-    let mut curr_op_code = v[0];
+use intcode::MachineError;
+
+/// Runs a day-2 program in place. Memory does not grow, so every address has
+/// to lie within the program, and reading past its end reads 0 like
+/// `intcode::Machine` does.
+fn get_mutated_sequence(v: &mut [i64]) -> Result<&mut [i64], MachineError> {
     let mut current_pos = 0;
 
-    while curr_op_code != 99 {
-        let l_value = v[v[current_pos + 1] as usize];
-        let r_value = v[v[current_pos + 2] as usize];
-        let output_pos = v[current_pos + 3] as usize;
-        match curr_op_code {
-            1 => v[output_pos] = l_value + r_value,
-            2 => v[output_pos] = l_value * r_value,
-            _ => println!("unknown op code {}", curr_op_code),
-        }
+    loop {
+        let read = |pos: usize| v.get(pos).copied().unwrap_or(0);
+        let curr_op_code = read(current_pos);
+        let operation: fn(i64, i64) -> Option<i64> = match curr_op_code {
+            1 => i64::checked_add,
+            2 => i64::checked_mul,
+            99 => return Ok(v),
+            _ => {
+                return Err(MachineError::UnknownOpcode {
+                    ip: current_pos,
+                    value: curr_op_code,
+                })
+            }
+        };
+        let address = |offset: usize| match read(current_pos + offset) {
+            x if x >= 0 && (x as usize) < v.len() => Ok(x as usize),
+            x => Err(MachineError::InvalidAddress {
+                ip: current_pos,
+                address: x,
+            }),
+        };
+        let l_value = v[address(1)?];
+        let r_value = v[address(2)?];
+        let output_pos = address(3)?;
+        v[output_pos] =
+            operation(l_value, r_value).ok_or(MachineError::Overflow { ip: current_pos })?;
         current_pos += 4;
-        curr_op_code = v[current_pos];
     }
-
-    v
 }
 
 fn main() {
@@ -28,20 +45,16 @@ fn main() {
     }
     let filename = &args[1];
     println!("input file is: {}", filename);
-    let file_content = fs::read_to_string(filename).expect("Could not open input file");
-
-    let input_sequence: Vec<i32> = file_content
-        .split(',')
-        .map(|x| i32::from_str_radix(x, 10).unwrap())
-        .collect();
+    let input_sequence =
+        intcode::load_program(filename).unwrap_or_else(|error| panic!("{}", error));
 
     for noun in 0..99 {
         for verb in 0..99 {
             let mut test_sequence = input_sequence.clone();
             test_sequence[1] = noun;
             test_sequence[2] = verb;
-            get_mutated_sequence(&mut test_sequence);
-            if test_sequence[0] == 19690720 {
+            let result = get_mutated_sequence(&mut test_sequence);
+            if result.is_ok() && test_sequence[0] == 19690720 {
                 println!("Noun: {}, Verb: {}", noun, verb);
             }
         }
@@ -77,3 +90,39 @@ fn test_input_sequence4() {
     println!("{:?}", get_mutated_sequence(&mut input));
     assert_eq!(input, [30, 1, 1, 4, 2, 5, 6, 0, 99]);
 }
+
+#[test]
+fn test_invalid_programs() {
+    assert_eq!(
+        Err(MachineError::UnknownOpcode { ip: 4, value: 42 }),
+        get_mutated_sequence(&mut [1, 0, 0, 0, 42]).map(|_| ())
+    );
+    assert_eq!(
+        Err(MachineError::InvalidAddress { ip: 0, address: 40 }),
+        get_mutated_sequence(&mut [1, 0, 0, 40, 99]).map(|_| ())
+    );
+    assert_eq!(
+        Err(MachineError::UnknownOpcode { ip: 4, value: 0 }),
+        get_mutated_sequence(&mut [1, 0, 0, 0]).map(|_| ())
+    );
+    assert_eq!(
+        Err(MachineError::Overflow { ip: 0 }),
+        get_mutated_sequence(&mut [2, 5, 5, 0, 99, i64::MAX]).map(|_| ())
+    );
+}
+
+#[test]
+fn test_errors_match_machine() {
+    use intcode::Machine;
+
+    for program in [
+        vec![1, 0, 0, 0, 42],
+        vec![1, 0, 0, 0],
+        vec![2, 5, 5, 0, 99, i64::MAX],
+    ] {
+        assert_eq!(
+            Machine::new(program.clone()).run().map(|_| ()),
+            get_mutated_sequence(&mut program.clone()).map(|_| ())
+        );
+    }
+}
@@ -1,33 +1,104 @@
-#[derive(Debug, Clone)]
-struct Coordinate {
-    x: usize,
-    y: usize,
+use grid::{Bounds, Grid, Point, SparseGrid};
+
+#[derive(Debug, PartialEq)]
+struct WireStats {
+    length: usize,
+    cells_covered: usize,
+    self_crossings: Vec<Point>,
+    bounds: Bounds,
 }
 
-impl PartialEq for Coordinate {
-    fn eq(&self, other: &Self) -> bool {
-        self.y == other.y && self.x == other.x
+fn get_wire_grid(line: &[Point]) -> SparseGrid<usize> {
+    let mut wire: SparseGrid<usize> = Grid::new();
+    for (steps, &coord) in line.iter().enumerate() {
+        if !wire.contains(coord) {
+            wire.insert(coord, steps);
+        }
     }
+    wire
+}
+
+fn find_crossings(wire1: &SparseGrid<usize>, wire2: &SparseGrid<usize>) -> Vec<Point> {
+    let mut result: Vec<Point> = wire1
+        .iter()
+        .map(|(coord, _)| coord)
+        .filter(|&coord| wire2.contains(coord))
+        .collect();
+    result.sort();
+    result
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Direction {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
 }
 
-fn find_crossings_from_coordinates(line1: &[Coordinate], line2: &[Coordinate]) -> Vec<Coordinate> {
-    let mut result: Vec<Coordinate> = vec![];
-    for coord in line1.iter() {
-        if line2.iter().filter(|&x| *x == *coord).count() > 0 {
-            result.push(coord.clone());
+impl Direction {
+    fn from_str(x: &str) -> Option<Direction> {
+        match x {
+            "U" | "N" => Some(Direction::North),
+            "NE" => Some(Direction::NorthEast),
+            "R" | "E" => Some(Direction::East),
+            "SE" => Some(Direction::SouthEast),
+            "D" | "S" => Some(Direction::South),
+            "SW" => Some(Direction::SouthWest),
+            "L" | "W" => Some(Direction::West),
+            "NW" => Some(Direction::NorthWest),
+            _ => None,
+        }
+    }
+
+    fn offset(self) -> Point {
+        match self {
+            Direction::North => Point::new(0, -1),
+            Direction::NorthEast => Point::new(1, -1),
+            Direction::East => Point::new(1, 0),
+            Direction::SouthEast => Point::new(1, 1),
+            Direction::South => Point::new(0, 1),
+            Direction::SouthWest => Point::new(-1, 1),
+            Direction::West => Point::new(-1, 0),
+            Direction::NorthWest => Point::new(-1, -1),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum DistanceMetric {
+    Manhattan,
+    Chebyshev,
+}
+
+impl DistanceMetric {
+    fn from_str(x: &str) -> Option<DistanceMetric> {
+        match x {
+            "manhattan" => Some(DistanceMetric::Manhattan),
+            "chebyshev" => Some(DistanceMetric::Chebyshev),
+            _ => None,
+        }
+    }
+
+    fn distance(self, a: Point, b: Point) -> i64 {
+        match self {
+            DistanceMetric::Manhattan => a.manhattan_distance(b),
+            DistanceMetric::Chebyshev => a.chebyshev_distance(b),
         }
     }
-    result
 }
 
-fn get_best_score(crossings: &[Coordinate], central_point: &Coordinate) -> i32 {
-    let mut best_score: i32 = std::i32::MAX;
-    for n in crossings.iter() {
-        if *n == *central_point {
+fn get_best_score(crossings: &[Point], central_point: Point, metric: DistanceMetric) -> i64 {
+    let mut best_score: i64 = i64::MAX;
+    for &n in crossings.iter() {
+        if n == central_point {
             continue;
         }
-        let score = (n.x as i32 - central_point.x as i32).abs()
-            + (n.y as i32 - central_point.y as i32).abs();
+        let score = metric.distance(n, central_point);
         if score < best_score {
             best_score = score;
         }
@@ -35,64 +106,82 @@ fn get_best_score(crossings: &[Coordinate], central_point: &Coordinate) -> i32 {
     best_score
 }
 
-fn get_coordinates_from_sequence(line: &[&str], central_point: &Coordinate) -> Vec<Coordinate> {
-    let mut coordinates: Vec<Coordinate> = vec![];
-    let mut current_pos = central_point.clone();
-    coordinates.push(current_pos.clone());
+fn parse_move(step: &str) -> Result<(Direction, usize), &'static str> {
+    let split_at = step
+        .find(|c: char| c.is_ascii_digit())
+        .ok_or("Missing move length")?;
+    let direction = Direction::from_str(&step[..split_at]).ok_or("Unknown move direction")?;
+    let steps = step[split_at..]
+        .parse()
+        .map_err(|_| "Invalid move length")?;
+    Ok((direction, steps))
+}
+
+fn get_coordinates_from_sequence(
+    line: &[&str],
+    central_point: Point,
+) -> Result<Vec<Point>, &'static str> {
+    let mut coordinates: Vec<Point> = vec![];
+    let mut current_pos = central_point;
+    coordinates.push(current_pos);
     for &step in line.iter() {
-        let steps: usize = step[1..].parse().unwrap();
-        match &step.chars().nth(0) {
-            Some('R') => {
-                for _ in 0..steps {
-                    current_pos.x += 1;
-                    coordinates.push(current_pos.clone());
-                }
-            }
-            Some('L') => {
-                for _ in 0..steps {
-                    current_pos.x -= 1;
-                    coordinates.push(current_pos.clone());
-                }
-            }
-            Some('U') => {
-                for _ in 0..steps {
-                    current_pos.y -= 1;
-                    coordinates.push(current_pos.clone());
-                }
-            }
-            Some('D') => {
-                for _ in 0..steps {
-                    current_pos.y += 1;
-                    coordinates.push(current_pos.clone());
-                }
-            }
-            Some(_) | None => panic!("Failed to parse move!"),
+        let (direction, steps) = parse_move(step)?;
+        for _ in 0..steps {
+            current_pos = current_pos + direction.offset();
+            coordinates.push(current_pos);
         }
     }
-    coordinates
+    Ok(coordinates)
 }
 
 fn get_steps_to_coordinate(
-    line: &[Coordinate],
-    coordinate: &Coordinate,
-) -> Result<i32, &'static str> {
-    match line
-        .iter()
-        .position(|x| x.x == coordinate.x && x.y == coordinate.y)
-    {
-        Some(x) => Ok(x as i32),
+    wire: &SparseGrid<usize>,
+    coordinate: Point,
+) -> Result<usize, &'static str> {
+    match wire.get(coordinate) {
+        Some(&x) => Ok(x),
         None => Err("Not found on line"),
     }
 }
 
+fn get_wire_stats(line: &[Point]) -> WireStats {
+    let mut visits: SparseGrid<usize> = Grid::new();
+    let mut self_crossings: Vec<Point> = vec![];
+
+    for &coord in line.iter() {
+        match visits.get_mut(coord) {
+            Some(count) => {
+                *count += 1;
+                if *count == 2 {
+                    self_crossings.push(coord);
+                }
+            }
+            None => {
+                visits.insert(coord, 1);
+            }
+        }
+    }
+
+    WireStats {
+        length: line.len() - 1,
+        cells_covered: visits.len(),
+        self_crossings,
+        bounds: visits.bounds().unwrap(),
+    }
+}
+
 fn main() {
     let args: Vec<String> = std::env::args().collect();
 
-    if args.len() != 2 {
+    if args.len() != 2 && args.len() != 3 {
         panic!("Expected file input");
     }
 
     let filename = &args[1];
+    let metric = match args.get(2) {
+        Some(name) => DistanceMetric::from_str(name).expect("Unknown distance metric"),
+        None => DistanceMetric::Manhattan,
+    };
     let filecontent = std::fs::read_to_string(filename).unwrap();
 
     let lines: Vec<&str> = filecontent.split('\n').collect();
@@ -102,32 +191,47 @@ fn main() {
         line_vecs.push(line.split(',').collect());
     }
 
-    let central_point = Coordinate { x: 25000, y: 25000 };
+    let central_point = Point::ORIGIN;
 
-    let line1 = get_coordinates_from_sequence(&line_vecs[0], &central_point);
-    let line2 = get_coordinates_from_sequence(&line_vecs[1], &central_point);
-    let crossings = find_crossings_from_coordinates(&line1, &line2);
-    let best_score = get_best_score(&crossings, &central_point);
-    let mut steps_counts: Vec<i32> = vec![];
+    let line1 = get_coordinates_from_sequence(&line_vecs[0], central_point).unwrap();
+    let line2 = get_coordinates_from_sequence(&line_vecs[1], central_point).unwrap();
+    let wire1 = get_wire_grid(&line1);
+    let wire2 = get_wire_grid(&line2);
+    let crossings = find_crossings(&wire1, &wire2);
+    let best_score = get_best_score(&crossings, central_point, metric);
+    let mut steps_counts: Vec<usize> = vec![];
 
-    for crossing in crossings.iter() {
-        if *crossing == central_point {
+    for &crossing in crossings.iter() {
+        if crossing == central_point {
             continue;
         }
-        let steps_line_1 = get_steps_to_coordinate(&line1, crossing).unwrap();
-        let steps_line_2 = get_steps_to_coordinate(&line2, crossing).unwrap();
+        let steps_line_1 = get_steps_to_coordinate(&wire1, crossing).unwrap();
+        let steps_line_2 = get_steps_to_coordinate(&wire2, crossing).unwrap();
         steps_counts.push(steps_line_1 + steps_line_2);
     }
 
     let min = *steps_counts.iter().min().unwrap();
 
-    println!("Best manhattan distance: {}", best_score);
+    println!("Best {:?} distance: {}", metric, best_score);
     println!("Best step count: {}", min);
+
+    for (n, line) in [&line1, &line2].iter().enumerate() {
+        let stats = get_wire_stats(line);
+        println!(
+            "Wire {}: length {}, cells covered {}, self crossings {}, bounding box {:?} - {:?}",
+            n + 1,
+            stats.length,
+            stats.cells_covered,
+            stats.self_crossings.len(),
+            stats.bounds.min,
+            stats.bounds.max
+        );
+    }
 }
 
 #[test]
 fn test_get_all_coordinates() {
-    let steps = vec![
+    let steps = [
         vec![
             "R98", "U47", "R26", "D63", "R33", "U87", "L62", "D20", "R33", "U53", "R51",
         ],
@@ -136,22 +240,24 @@ fn test_get_all_coordinates() {
         ],
     ];
 
-    let central_point = Coordinate { x: 3000, y: 3000 };
+    let central_point = Point::new(3000, 3000);
 
-    let line1 = get_coordinates_from_sequence(&steps[0], &central_point);
-    let line2 = get_coordinates_from_sequence(&steps[1], &central_point);
-    let crossings = find_crossings_from_coordinates(&line1, &line2);
+    let line1 = get_coordinates_from_sequence(&steps[0], central_point).unwrap();
+    let line2 = get_coordinates_from_sequence(&steps[1], central_point).unwrap();
+    let wire1 = get_wire_grid(&line1);
+    let wire2 = get_wire_grid(&line2);
+    let crossings = find_crossings(&wire1, &wire2);
 
-    let best_score = get_best_score(&crossings, &central_point);
+    let best_score = get_best_score(&crossings, central_point, DistanceMetric::Manhattan);
 
-    let mut steps_counts: Vec<i32> = vec![];
+    let mut steps_counts: Vec<usize> = vec![];
 
-    for crossing in crossings.iter() {
-        if *crossing == central_point {
+    for &crossing in crossings.iter() {
+        if crossing == central_point {
             continue;
         }
-        let steps_line_1 = get_steps_to_coordinate(&line1, crossing).unwrap();
-        let steps_line_2 = get_steps_to_coordinate(&line2, crossing).unwrap();
+        let steps_line_1 = get_steps_to_coordinate(&wire1, crossing).unwrap();
+        let steps_line_2 = get_steps_to_coordinate(&wire2, crossing).unwrap();
         steps_counts.push(steps_line_1 + steps_line_2);
         println!("Steps: {}", steps_line_1 + steps_line_2);
     }
@@ -161,28 +267,77 @@ fn test_get_all_coordinates() {
 
 #[test]
 fn test_get_all_coordinates2() {
-    let steps = vec![
+    let steps = [
         vec!["R75", "D30", "R83", "U83", "L12", "D49", "R71", "U7", "L72"],
         vec!["U62", "R66", "U55", "R34", "D71", "R55", "D58", "R83"],
     ];
 
-    let central_point = Coordinate { x: 3000, y: 3000 };
+    let central_point = Point::new(3000, 3000);
 
-    let line1 = get_coordinates_from_sequence(&steps[0], &central_point);
-    let line2 = get_coordinates_from_sequence(&steps[1], &central_point);
-    let crossings = find_crossings_from_coordinates(&line1, &line2);
-    let best_score = get_best_score(&crossings, &central_point);
+    let line1 = get_coordinates_from_sequence(&steps[0], central_point).unwrap();
+    let line2 = get_coordinates_from_sequence(&steps[1], central_point).unwrap();
+    let wire1 = get_wire_grid(&line1);
+    let wire2 = get_wire_grid(&line2);
+    let crossings = find_crossings(&wire1, &wire2);
+    let best_score = get_best_score(&crossings, central_point, DistanceMetric::Manhattan);
 
-    let mut steps_counts: Vec<i32> = vec![];
+    let mut steps_counts: Vec<usize> = vec![];
 
-    for crossing in crossings.iter() {
-        if *crossing == central_point {
+    for &crossing in crossings.iter() {
+        if crossing == central_point {
             continue;
         }
-        let steps_line_1 = get_steps_to_coordinate(&line1, crossing).unwrap();
-        let steps_line_2 = get_steps_to_coordinate(&line2, crossing).unwrap();
+        let steps_line_1 = get_steps_to_coordinate(&wire1, crossing).unwrap();
+        let steps_line_2 = get_steps_to_coordinate(&wire2, crossing).unwrap();
         steps_counts.push(steps_line_1 + steps_line_2);
     }
     assert_eq!(610, *steps_counts.iter().min().unwrap());
     assert_eq!(159, best_score);
 }
+
+#[test]
+fn test_parse_move() {
+    assert_eq!(Ok((Direction::East, 98)), parse_move("R98"));
+    assert_eq!(Ok((Direction::NorthEast, 12)), parse_move("NE12"));
+    assert_eq!(Ok((Direction::SouthWest, 3)), parse_move("SW3"));
+    assert_eq!(Err("Unknown move direction"), parse_move("X5"));
+    assert_eq!(Err("Missing move length"), parse_move("NE"));
+}
+
+#[test]
+fn test_diagonal_crossings() {
+    let steps = [vec!["NE4"], vec!["E4", "N4", "SW4"]];
+
+    let central_point = Point::new(3000, 3000);
+
+    let line1 = get_coordinates_from_sequence(&steps[0], central_point).unwrap();
+    let line2 = get_coordinates_from_sequence(&steps[1], central_point).unwrap();
+    let wire1 = get_wire_grid(&line1);
+    let wire2 = get_wire_grid(&line2);
+    let crossings = find_crossings(&wire1, &wire2);
+
+    assert_eq!(
+        2,
+        get_best_score(&crossings, central_point, DistanceMetric::Manhattan)
+    );
+    assert_eq!(
+        1,
+        get_best_score(&crossings, central_point, DistanceMetric::Chebyshev)
+    );
+}
+
+#[test]
+fn test_wire_stats() {
+    let steps = vec!["R4", "D2", "L2", "U4"];
+
+    let central_point = Point::new(3000, 3000);
+
+    let line = get_coordinates_from_sequence(&steps, central_point).unwrap();
+    let stats = get_wire_stats(&line);
+
+    assert_eq!(12, stats.length);
+    assert_eq!(12, stats.cells_covered);
+    assert_eq!(vec![Point::new(3002, 3000)], stats.self_crossings);
+    assert_eq!(Point::new(3000, 2998), stats.bounds.min);
+    assert_eq!(Point::new(3004, 3002), stats.bounds.max);
+}
@@ -0,0 +1,84 @@
+use crate::count::PairRule;
+
+/// Smallest number `>= n` whose digits never decrease, `None` if it does not
+/// fit in a `u64`.
+pub fn next_non_decreasing(n: u64) -> Option<u64> {
+    let mut digits: Vec<u64> = n.to_string().bytes().map(|b| u64::from(b - b'0')).collect();
+    if let Some(pos) = (1..digits.len()).find(|&i| digits[i] < digits[i - 1]) {
+        let fill = digits[pos - 1];
+        for digit in digits[pos..].iter_mut() {
+            *digit = fill;
+        }
+    }
+    digits
+        .iter()
+        .try_fold(0u64, |acc, &digit| acc.checked_mul(10)?.checked_add(digit))
+}
+
+/// Iterator over every number with non-decreasing digits in a range, jumping
+/// straight from one candidate to the next instead of testing each number.
+pub struct Candidates {
+    next: Option<u64>,
+    upper_limit: u64,
+}
+
+pub fn candidates(lower_limit: u64, upper_limit: u64) -> Candidates {
+    Candidates {
+        next: next_non_decreasing(lower_limit),
+        upper_limit,
+    }
+}
+
+impl Iterator for Candidates {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        let current = self.next.filter(|&x| x <= self.upper_limit)?;
+        self.next = current.checked_add(1).and_then(next_non_decreasing);
+        Some(current)
+    }
+}
+
+/// Lazily yields the valid passwords in a range.
+pub fn valid_passwords(
+    lower_limit: u64,
+    upper_limit: u64,
+    pair_rule: PairRule,
+) -> impl Iterator<Item = u64> {
+    candidates(lower_limit, upper_limit).filter(move |&x| match pair_rule {
+        PairRule::AtLeastTwo => crate::is_valid_password(&x.to_string(), 10) == Ok(true),
+        PairRule::ExactlyTwo => crate::is_valid_improved_password(&x.to_string(), 10) == Ok(true),
+    })
+}
+
+#[test]
+fn test_next_non_decreasing() {
+    assert_eq!(Some(156_666), next_non_decreasing(156_218));
+    assert_eq!(Some(123_789), next_non_decreasing(123_789));
+    assert_eq!(Some(2222), next_non_decreasing(2000));
+    assert_eq!(None, next_non_decreasing(u64::MAX));
+}
+
+#[test]
+fn test_candidates() {
+    let first: Vec<u64> = candidates(156_218, 652_527).take(3).collect();
+    assert_eq!(vec![156_666, 156_667, 156_668], first);
+
+    let brute_force = (0..=20_000u64)
+        .filter(|&x| x.to_string().as_bytes().windows(2).all(|w| w[0] <= w[1]))
+        .count();
+    assert_eq!(brute_force, candidates(0, 20_000).count());
+
+    let last: Vec<u64> = candidates(17_999_999_999_999_999_990, u64::MAX).collect();
+    assert_eq!(vec![17_999_999_999_999_999_999], last);
+}
+
+#[test]
+fn test_valid_passwords() {
+    for &pair_rule in &[PairRule::AtLeastTwo, PairRule::ExactlyTwo] {
+        assert_eq!(
+            Ok(valid_passwords(156_218, 652_527, pair_rule).count() as u128),
+            crate::count::count_in_range(156_218, 652_527, pair_rule, 10)
+        );
+    }
+}
@@ -0,0 +1,279 @@
+use crate::rules::{
+    Alphabet, DigitsOnly, HasRunOfAtLeast, HasRunOfLength, Length, MaxRunLength, NonDecreasing,
+    NonIncreasing, PasswordRule, RuleSet, WithinRange,
+};
+
+/// A parsed policy file. `radix` is set when the file names one, and is the
+/// radix ranges are counted and listed in.
+pub struct Policy {
+    pub rules: RuleSet,
+    pub radix: Option<u32>,
+}
+
+/// The line up to a `#` comment, leaving `#` inside quoted values alone.
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (pos, chr) in line.char_indices() {
+        match chr {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..pos],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// Splits on every `separator` that is not inside a quoted value.
+fn split_outside_quotes<'a>(line: &'a str, separator: &str) -> Vec<&'a str> {
+    let mut parts = vec![];
+    let mut quoted = false;
+    let mut start = 0;
+    for (pos, chr) in line.char_indices() {
+        if chr == '"' {
+            quoted = !quoted;
+        } else if !quoted && pos >= start && line[pos..].starts_with(separator) {
+            parts.push(&line[start..pos]);
+            start = pos + separator.len();
+        }
+    }
+    parts.push(&line[start..]);
+    parts
+}
+
+fn key_value(n: usize, text: &str) -> Result<(&str, &str), String> {
+    match text.find('=') {
+        Some(pos) => Ok((text[..pos].trim(), text[pos + 1..].trim().trim_matches('"'))),
+        None => Err(format!("line {}: expected `key = value`", n + 1)),
+    }
+}
+
+/// The rule for one `key = value` pair, `None` for `monotonic = none`.
+fn parse_rule(n: usize, key: &str, value: &str) -> Result<Option<Box<dyn PasswordRule>>, String> {
+    let number = || {
+        value
+            .parse::<usize>()
+            .map_err(|_| format!("line {}: `{}` is not a number", n + 1, value))
+    };
+    let rule: Box<dyn PasswordRule> = match key {
+        "length" => Box::new(Length(number()?)),
+        "alphabet" => Box::new(Alphabet(value.to_string())),
+        "monotonic" => match value {
+            "non-decreasing" => Box::new(NonDecreasing),
+            "non-increasing" => Box::new(NonIncreasing),
+            "none" => return Ok(None),
+            _ => return Err(format!("line {}: unknown direction `{}`", n + 1, value)),
+        },
+        "min_run" => Box::new(HasRunOfAtLeast(number()?)),
+        "exact_run" => Box::new(HasRunOfLength(number()?)),
+        "max_run" => Box::new(MaxRunLength(number()?)),
+        _ => return Err(format!("line {}: unknown key `{}`", n + 1, key)),
+    };
+    Ok(Some(rule))
+}
+
+/// A line of rules joined with `or` and `and`, where `and` binds tighter and
+/// `not` in front of a key negates that one rule.
+fn parse_combined(n: usize, line: &str) -> Result<Box<dyn PasswordRule>, String> {
+    let mut alternatives = vec![];
+    for alternative in split_outside_quotes(line, " or ") {
+        let mut terms = vec![];
+        for term in split_outside_quotes(alternative, " and ") {
+            let (negated, term) = match term.trim().strip_prefix("not ") {
+                Some(term) => (true, term),
+                None => (false, term),
+            };
+            let (key, value) = key_value(n, term)?;
+            let rule = match key {
+                "radix" | "min" | "max" => None,
+                _ => parse_rule(n, key, value)?,
+            }
+            .ok_or_else(|| {
+                format!(
+                    "line {}: `{}` cannot be combined with other rules",
+                    n + 1,
+                    term.trim()
+                )
+            })?;
+            terms.push(if negated { Box::new(rule.not()) } else { rule });
+        }
+        // Splitting always gives at least one part, so there is a rule here.
+        alternatives.push(terms.into_iter().reduce(|a, b| Box::new(a.and(b))).unwrap());
+    }
+    Ok(alternatives
+        .into_iter()
+        .reduce(|a, b| Box::new(a.or(b)))
+        .unwrap())
+}
+
+/// Builds a rule set from a policy file made of `key = value` lines, which is
+/// the flat subset of TOML. Blank lines and `#` comments are ignored.
+///
+/// Supported keys are `length`, `radix`, `alphabet`, `monotonic` (`non-decreasing`,
+/// `non-increasing` or `none`), `min_run`, `exact_run`, `max_run`, and `min` and
+/// `max` for the range of values, written in the policy's radix.
+///
+/// Going beyond TOML, a line may combine rules, as in
+/// `exact_run = 2 or min_run = 4 and not exact_run = 5`. `radix`, `min` and
+/// `max` apply to the whole policy and cannot be combined.
+pub fn parse_policy(content: &str) -> Result<Policy, String> {
+    let mut rules = RuleSet::new();
+    let mut radix = None;
+    let mut limits: [Option<(usize, String)>; 2] = [None, None];
+
+    for (n, line) in content.lines().enumerate() {
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let combined = line.starts_with("not ")
+            || split_outside_quotes(line, " or ").len() > 1
+            || split_outside_quotes(line, " and ").len() > 1;
+        if combined {
+            rules = rules.with(parse_combined(n, line)?);
+            continue;
+        }
+
+        let (key, value) = key_value(n, line)?;
+        rules = match key {
+            "radix" => match value.parse::<usize>() {
+                Ok(value @ 2..=36) => {
+                    radix = Some(value as u32);
+                    rules.with(DigitsOnly(value as u32))
+                }
+                Ok(_) => return Err(format!("line {}: radix must be between 2 and 36", n + 1)),
+                Err(_) => return Err(format!("line {}: `{}` is not a number", n + 1, value)),
+            },
+            "min" => {
+                limits[0] = Some((n, value.to_string()));
+                rules
+            }
+            "max" => {
+                limits[1] = Some((n, value.to_string()));
+                rules
+            }
+            _ => match parse_rule(n, key, value)? {
+                Some(rule) => rules.with(rule),
+                None => rules,
+            },
+        };
+    }
+
+    // The limits are parsed last, since `radix` may come after them.
+    let limit_radix = radix.unwrap_or(10);
+    let mut parsed = [0, u128::MAX];
+    for (limit, parsed) in limits.iter().zip(parsed.iter_mut()) {
+        if let Some((n, value)) = limit {
+            *parsed = u128::from_str_radix(value, limit_radix).map_err(|_| {
+                format!(
+                    "line {}: `{}` is not a base {} number",
+                    n + 1,
+                    value,
+                    limit_radix
+                )
+            })?;
+        }
+    }
+    if limits.iter().any(Option::is_some) {
+        rules = rules.with(WithinRange {
+            lower_limit: parsed[0],
+            upper_limit: parsed[1],
+            radix: limit_radix,
+        });
+    }
+
+    Ok(Policy { rules, radix })
+}
+
+pub fn load_policy(filename: &str) -> Result<Policy, String> {
+    let content = std::fs::read_to_string(filename)
+        .map_err(|error| format!("could not read {}: {}", filename, error))?;
+    parse_policy(&content)
+}
+
+#[test]
+fn test_puzzle_policy() {
+    use crate::rules::PasswordRule;
+
+    let policy = parse_policy(include_str!("../policies/puzzle.toml")).unwrap();
+    assert_eq!(None, policy.radix);
+    let rules = policy.rules;
+    assert!(rules.check("112233"));
+    assert!(!rules.check("123444"));
+    assert!(!rules.check("1122"));
+    assert_eq!(
+        Ok(rules.count_in_range(156_218, 652_527, 10) as u128),
+        crate::count_improved_valid_passwords(156_218, 652_527, 10)
+    );
+}
+
+#[test]
+fn test_policy_errors() {
+    assert_eq!(
+        Err("line 2: unknown key `colour`".to_string()),
+        parse_policy("length = 4\ncolour = \"red\"").map(|_| ())
+    );
+    assert_eq!(
+        Err("line 1: `six` is not a number".to_string()),
+        parse_policy("length = six").map(|_| ())
+    );
+    assert_eq!(
+        Err("line 1: expected `key = value`".to_string()),
+        parse_policy("length").map(|_| ())
+    );
+    assert_eq!(
+        Err("line 1: `1g` is not a base 16 number".to_string()),
+        parse_policy("min = 1g\nradix = 16").map(|_| ())
+    );
+}
+
+#[test]
+fn test_policy_range() {
+    use crate::rules::PasswordRule;
+
+    let policy = parse_policy("radix = 16\nmin = \"a0\"\nmax = ff").unwrap();
+    assert_eq!(Some(16), policy.radix);
+    let rules = policy.rules;
+    assert!(rules.check("aa"));
+    assert!(!rules.check("99"));
+    assert_eq!("only base 16 digits, between a0 and ff", rules.describe());
+}
+
+#[test]
+fn test_comments_outside_quotes() {
+    use crate::rules::PasswordRule;
+
+    let rules = parse_policy("alphabet = \"#$%\" # symbols only\n# length = 2")
+        .unwrap()
+        .rules;
+    assert!(rules.check("#%"));
+    assert!(!rules.check("#a"));
+    assert_eq!("only characters from \"#$%\"", rules.describe());
+}
+
+#[test]
+fn test_combined_rules() {
+    let rules = parse_policy("length = 6\nexact_run = 2 or min_run = 4 and not exact_run = 5")
+        .unwrap()
+        .rules;
+    assert!(rules.check("112345"));
+    assert!(rules.check("111123"));
+    assert!(!rules.check("111112"));
+    assert!(!rules.check("111234"));
+    assert_eq!(
+        "length 6, a run of exactly 2 or a run of at least 4 and not a run of exactly 5",
+        rules.describe()
+    );
+    assert!(parse_policy("alphabet = \"not a or b\"")
+        .unwrap()
+        .rules
+        .check("ab or"));
+    assert_eq!(
+        Err("line 1: `radix = 16` cannot be combined with other rules".to_string()),
+        parse_policy("length = 2 or radix = 16").map(|_| ())
+    );
+    assert_eq!(
+        Err("line 1: unknown key `colour`".to_string()),
+        parse_policy("not colour = red").map(|_| ())
+    );
+}
@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+
+use crate::radix::{check_radix, to_digits, PasswordError};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PairRule {
+    /// At least two adjacent digits are the same.
+    AtLeastTwo,
+    /// Some run of equal digits is exactly two long.
+    ExactlyTwo,
+}
+
+/// Validator state after placing some digits of a non-decreasing number.
+/// Run lengths above three behave the same for both rules, so they are capped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct State {
+    last_digit: u8,
+    run_len: u8,
+    found: bool,
+}
+
+impl State {
+    fn push(self, digit: u8, pair_rule: PairRule) -> State {
+        if digit == self.last_digit {
+            return State {
+                run_len: (self.run_len + 1).min(3),
+                ..self
+            };
+        }
+        State {
+            last_digit: digit,
+            run_len: 1,
+            found: self.is_valid(pair_rule),
+        }
+    }
+
+    fn is_valid(self, pair_rule: PairRule) -> bool {
+        self.found
+            || match pair_rule {
+                PairRule::AtLeastTwo => self.run_len >= 2,
+                PairRule::ExactlyTwo => self.run_len == 2,
+            }
+    }
+}
+
+struct Counter {
+    digits: Vec<u8>,
+    radix: u32,
+    pair_rule: PairRule,
+    memo: HashMap<(usize, Option<State>), u128>,
+}
+
+impl Counter {
+    /// Counts valid completions of the number from `pos` onwards. `tight` means
+    /// every digit so far equals the upper limit, `state` is `None` while only
+    /// leading zeros have been placed.
+    fn count(&mut self, pos: usize, tight: bool, state: Option<State>) -> u128 {
+        if pos == self.digits.len() {
+            return match state {
+                Some(state) if state.is_valid(self.pair_rule) => 1,
+                _ => 0,
+            };
+        }
+        if !tight {
+            if let Some(&count) = self.memo.get(&(pos, state)) {
+                return count;
+            }
+        }
+
+        let max_digit = if tight {
+            self.digits[pos]
+        } else {
+            (self.radix - 1) as u8
+        };
+        let min_digit = state.map_or(0, |state| state.last_digit);
+        let mut total = 0;
+        for digit in min_digit..=max_digit {
+            let next_state = match state {
+                None if digit == 0 => None,
+                None => Some(State {
+                    last_digit: digit,
+                    run_len: 1,
+                    found: false,
+                }),
+                Some(state) => Some(state.push(digit, self.pair_rule)),
+            };
+            total += self.count(pos + 1, tight && digit == max_digit, next_state);
+        }
+
+        if !tight {
+            self.memo.insert((pos, state), total);
+        }
+        total
+    }
+}
+
+/// Number of valid passwords in `0..=upper_limit`.
+fn count_up_to(upper_limit: u128, pair_rule: PairRule, radix: u32) -> u128 {
+    let mut counter = Counter {
+        digits: to_digits(upper_limit, radix),
+        radix,
+        pair_rule,
+        memo: HashMap::new(),
+    };
+    counter.count(0, true, None)
+}
+
+/// Counts numbers in the range whose digits in `radix` never decrease and
+/// satisfy `pair_rule`, without visiting each of them, so any `u128` range
+/// completes instantly.
+pub fn count_in_range(
+    lower_limit: u128,
+    upper_limit: u128,
+    pair_rule: PairRule,
+    radix: u32,
+) -> Result<u128, PasswordError> {
+    check_radix(radix)?;
+    if lower_limit > upper_limit {
+        return Ok(0);
+    }
+    let below = match lower_limit {
+        0 => 0,
+        _ => count_up_to(lower_limit - 1, pair_rule, radix),
+    };
+    Ok(count_up_to(upper_limit, pair_rule, radix) - below)
+}
+
+#[test]
+fn test_matches_puzzle_counts() {
+    assert_eq!(
+        Ok(1694),
+        count_in_range(156_218, 652_527, PairRule::AtLeastTwo, 10)
+    );
+    assert_eq!(
+        Ok(1148),
+        count_in_range(156_218, 652_527, PairRule::ExactlyTwo, 10)
+    );
+}
+
+#[test]
+fn test_matches_rule_engine() {
+    use crate::rules::{improved_password_rules, password_rules, PasswordRule};
+
+    // The original loops started from '0' as the previous digit, so they
+    // accepted "0" as containing a double. Since the rule engine, "0" is
+    // invalid like every other single digit.
+    assert_eq!(0, password_rules(10).count_in_range(0, 0, 10));
+    assert_eq!(Ok(0), count_in_range(0, 0, PairRule::AtLeastTwo, 10));
+    for &radix in &[2, 10, 16, 36] {
+        for &(lower, upper) in &[(0, 2000), (1, 1), (99, 123), (9_990, 12_345)] {
+            assert_eq!(
+                Ok(password_rules(radix).count_in_range(lower, upper, radix) as u128),
+                count_in_range(lower, upper, PairRule::AtLeastTwo, radix)
+            );
+            assert_eq!(
+                Ok(improved_password_rules(radix).count_in_range(lower, upper, radix) as u128),
+                count_in_range(lower, upper, PairRule::ExactlyTwo, radix)
+            );
+        }
+    }
+}
+
+#[test]
+fn test_full_range() {
+    let total = count_in_range(0, u128::MAX, PairRule::AtLeastTwo, 10).unwrap();
+    let exact = count_in_range(0, u128::MAX, PairRule::ExactlyTwo, 10).unwrap();
+    assert!(exact < total);
+    assert!(count_in_range(0, u128::MAX, PairRule::ExactlyTwo, 2).is_ok());
+    assert_eq!(Ok(0), count_in_range(10, 9, PairRule::AtLeastTwo, 10));
+    assert_eq!(
+        Err(PasswordError::InvalidRadix(1)),
+        count_in_range(0, 9, PairRule::AtLeastTwo, 1)
+    );
+}
@@ -1,86 +1,285 @@
-fn is_valid_password(password: &str) -> bool {
-    let mut last_char = '0';
-    let mut found_double = false;
-    for chr in password.chars() {
-        if !(chr >= '0' && chr <= '9' && chr >= last_char) {
-            return false;
-        }
-        if chr == last_char {
-            found_double = true;
-        }
+mod candidates;
+mod config;
+mod count;
+mod radix;
+mod rules;
 
-        last_char = chr;
-    }
-    found_double
+use std::convert::TryFrom;
+
+use count::PairRule;
+use radix::{check_radix, parse_digits, PasswordError};
+use rules::{PasswordRule, Violation};
+
+fn is_valid_password(password: &str, radix: u32) -> Result<bool, PasswordError> {
+    parse_digits(password, radix)?;
+    Ok(rules::password_rules(radix).check(password))
 }
 
-fn is_valid_improved_password(password: &str) -> bool {
-    let mut last_char = '0';
-    let mut found_double = false;
-    let mut sequence_len = 0;
-    let mut ended_double_digit = false;
+fn is_valid_improved_password(password: &str, radix: u32) -> Result<bool, PasswordError> {
+    parse_digits(password, radix)?;
+    Ok(rules::improved_password_rules(radix).check(password))
+}
 
-    for chr in password.chars() {
-        if !(chr >= '0' && chr <= '9' && chr >= last_char) {
-            return false;
-        }
+/// Every rule the password breaks, empty when it is valid.
+fn explain_password(password: &str, radix: u32) -> Result<Vec<Violation>, PasswordError> {
+    parse_digits(password, radix)?;
+    Ok(rules::password_rules(radix).violations(password))
+}
 
-        if chr == last_char {
-            found_double = true;
-            sequence_len += 1;
-        }
-        else {
-            if sequence_len == 1 {
-                ended_double_digit = true;
-            }
-            sequence_len = 0;
-        }
-        last_char = chr;
-    }
+fn explain_improved_password(password: &str, radix: u32) -> Result<Vec<Violation>, PasswordError> {
+    parse_digits(password, radix)?;
+    Ok(rules::improved_password_rules(radix).violations(password))
+}
+
+fn count_valid_passwords(
+    lower_limit: u128,
+    upper_limit: u128,
+    radix: u32,
+) -> Result<u128, PasswordError> {
+    count::count_in_range(lower_limit, upper_limit, PairRule::AtLeastTwo, radix)
+}
+
+fn count_improved_valid_passwords(
+    lower_limit: u128,
+    upper_limit: u128,
+    radix: u32,
+) -> Result<u128, PasswordError> {
+    count::count_in_range(lower_limit, upper_limit, PairRule::ExactlyTwo, radix)
+}
+
+#[cfg(test)]
+fn count_valid_both(
+    lower_limit: u128,
+    upper_limit: u128,
+    radix: u32,
+) -> Result<u128, PasswordError> {
+    check_radix(radix)?;
+    Ok(rules::password_rules(radix)
+        .and(rules::improved_password_rules(radix))
+        .count_in_range(lower_limit, upper_limit, radix) as u128)
+}
+
+/// The part of the range that fits in a `u64`, `None` when none of it does.
+fn clamp_to_u64(lower_limit: u128, upper_limit: u128) -> Option<(u64, u64)> {
+    let lower_limit = u64::try_from(lower_limit).ok()?;
+    Some((lower_limit, upper_limit.min(u128::from(u64::MAX)) as u64))
+}
 
-    found_double && (sequence_len == 1 || ended_double_digit)
+struct Options {
+    policy: Option<rules::RuleSet>,
+    radix: u32,
+    pair_rule: PairRule,
 }
 
-fn count_valid_passwords(lower_limit: i32, upper_limit: i32) -> i32 {
-    (lower_limit..=upper_limit)
-        .filter(|&x| is_valid_password(&*x.to_string()))
-        .count() as i32
+/// The policy file's radix wins, but `--radix` may not contradict it.
+fn resolve_radix(policy: Option<u32>, option: Option<u32>) -> Result<u32, String> {
+    match (policy, option) {
+        (Some(policy), Some(option)) if policy != option => Err(format!(
+            "--radix {} does not match the policy's radix {}",
+            option, policy
+        )),
+        (policy, option) => Ok(policy.or(option).unwrap_or(10)),
+    }
 }
 
-fn count_improved_valid_passwords(lower_limit: i32, upper_limit: i32) -> i32 {
-    (lower_limit..=upper_limit)
-        .filter(|&x| is_valid_improved_password(&*x.to_string()))
-        .count() as i32
+fn parse_options(args: &[String]) -> Options {
+    let mut policy: Option<config::Policy> = None;
+    let mut radix = None;
+    let mut pair_rule = PairRule::AtLeastTwo;
+    let mut options = args.iter();
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "--config" => {
+                let filename = options.next().expect("No policy file supplied!");
+                policy =
+                    Some(config::load_policy(filename).unwrap_or_else(|error| panic!("{}", error)));
+            }
+            "--radix" => {
+                let value = options
+                    .next()
+                    .and_then(|x| x.parse().ok())
+                    .expect("Invalid radix");
+                check_radix(value).unwrap_or_else(|error| panic!("{}", error));
+                radix = Some(value);
+            }
+            "--improved" => pair_rule = PairRule::ExactlyTwo,
+            _ => panic!("Unknown option {}", option),
+        }
+    }
+    let radix = resolve_radix(policy.as_ref().and_then(|policy| policy.radix), radix)
+        .unwrap_or_else(|error| panic!("{}", error));
+    Options {
+        policy: policy.map(|policy| policy.rules),
+        radix,
+        pair_rule,
+    }
 }
 
-fn count_valid_both(lower_limit: i32, upper_limit: i32) -> i32 {
-    (lower_limit..=upper_limit)
-        .filter(|&x| is_valid_password(&*x.to_string()) && is_valid_improved_password(&*x.to_string()))
-        .count() as i32
+fn print_explanation(name: &str, explanation: Result<Vec<Violation>, PasswordError>) {
+    match explanation {
+        Ok(ref violations) if violations.is_empty() => println!("{}: valid", name),
+        Ok(violations) => {
+            println!("{}: invalid", name);
+            for violation in violations {
+                println!("  - {}", violation);
+            }
+        }
+        Err(error) => println!("{}: malformed password, {}", name, error),
+    }
 }
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() >= 3 && args[1] == "explain" {
+        let password = &args[2];
+        let options = parse_options(&args[3..]);
+        match options.policy {
+            Some(policy) => print_explanation(
+                &format!("Policy ({})", policy.describe()),
+                Ok(policy.violations(password)),
+            ),
+            None => {
+                print_explanation("Password rules", explain_password(password, options.radix));
+                print_explanation(
+                    "Improved password rules",
+                    explain_improved_password(password, options.radix),
+                );
+            }
+        }
+        return;
+    }
+    if args.len() >= 4 && (args[1] == "count" || args[1] == "list") {
+        let Options {
+            policy,
+            radix,
+            pair_rule,
+        } = parse_options(&args[4..]);
+        let lower_limit = u128::from_str_radix(&args[2], radix).expect("Invalid lower limit");
+        let upper_limit = u128::from_str_radix(&args[3], radix).expect("Invalid upper limit");
+
+        if let Some(policy) = policy {
+            if args[1] == "count" {
+                println!(
+                    "Matching passwords: {}",
+                    policy.count_in_range(lower_limit, upper_limit, radix)
+                );
+            } else {
+                for password in policy.matching_in_range(lower_limit, upper_limit, radix) {
+                    println!("{}", password);
+                }
+            }
+            return;
+        }
+
+        if args[1] == "count" {
+            println!(
+                "Valid passwords: {}",
+                count_valid_passwords(lower_limit, upper_limit, radix).unwrap()
+            );
+            println!(
+                "Valid improved passwords: {}",
+                count_improved_valid_passwords(lower_limit, upper_limit, radix).unwrap()
+            );
+            return;
+        }
+
+        if radix != 10 {
+            panic!("Listing only supports decimal passwords without a policy file");
+        }
+        if let Some((lower_limit, upper_limit)) = clamp_to_u64(lower_limit, upper_limit) {
+            for password in candidates::valid_passwords(lower_limit, upper_limit, pair_rule) {
+                println!("{}", password);
+            }
+        }
+        return;
+    }
+
     println!(
         "Valid passwords: {}",
-        count_valid_passwords(156_218, 652_527)
+        count_valid_passwords(156_218, 652_527, 10).unwrap()
     );
 
     println!(
         "Valid improved passwords: {}",
-        count_improved_valid_passwords(156_218, 652_527)
+        count_improved_valid_passwords(156_218, 652_527, 10).unwrap()
     );
 }
 
 #[test]
 fn test_is_valid_password() {
-    assert_eq!(true, is_valid_password("111111"));
-    assert_eq!(false, is_valid_password("223450"));
-    assert_eq!(false, is_valid_password("123789"));
+    assert_eq!(Ok(true), is_valid_password("111111", 10));
+    assert_eq!(Ok(false), is_valid_password("223450", 10));
+    assert_eq!(Ok(false), is_valid_password("123789", 10));
 }
 
 #[test]
 fn test_is_valid_improved_password() {
-    assert_eq!(true, is_valid_improved_password("112233"));
-    assert_eq!(false, is_valid_improved_password("123444"));
-    assert_eq!(true, is_valid_improved_password("111122"));
+    assert_eq!(Ok(true), is_valid_improved_password("112233", 10));
+    assert_eq!(Ok(false), is_valid_improved_password("123444", 10));
+    assert_eq!(Ok(true), is_valid_improved_password("111122", 10));
+}
+
+#[test]
+fn test_count_valid_both() {
+    // Every run of exactly two is also an adjacent pair.
+    assert_eq!(
+        count_improved_valid_passwords(156_218, 652_527, 10),
+        count_valid_both(156_218, 652_527, 10)
+    );
+}
+
+#[test]
+fn test_radix_and_length() {
+    assert_eq!(Ok(true), is_valid_improved_password("0011", 2));
+    assert_eq!(Ok(true), is_valid_improved_password("19aaFz", 36));
+    assert_eq!(Ok(false), is_valid_password("fe", 16));
+    assert_eq!(Ok(true), is_valid_password("1234567899999999999999", 10));
+    assert_eq!(
+        Err(PasswordError::InvalidDigit {
+            position: 3,
+            chr: 'x',
+            radix: 10
+        }),
+        is_valid_password("112x", 10)
+    );
+    assert_eq!(Err(PasswordError::Empty), is_valid_password("", 10));
+    assert_eq!(
+        Err(PasswordError::InvalidRadix(0)),
+        count_valid_passwords(1, 2, 0)
+    );
+    assert!(count_valid_passwords(10u128.pow(11), 10u128.pow(18) - 1, 10).is_ok());
+    assert!(
+        count_improved_valid_passwords(0, u128::from(u64::MAX), 16).unwrap()
+            < count_valid_passwords(0, u128::from(u64::MAX), 16).unwrap()
+    );
+}
+
+#[test]
+fn test_clamp_to_u64() {
+    assert_eq!(Some((5, 10)), clamp_to_u64(5, 10));
+    assert_eq!(Some((5, u64::MAX)), clamp_to_u64(5, u128::MAX));
+    let above = u128::from(u64::MAX) + 1;
+    assert_eq!(None, clamp_to_u64(above, above + 4));
+}
+
+#[test]
+fn test_resolve_radix() {
+    assert_eq!(Ok(10), resolve_radix(None, None));
+    assert_eq!(Ok(16), resolve_radix(Some(16), None));
+    assert_eq!(Ok(16), resolve_radix(Some(16), Some(16)));
+    assert_eq!(Ok(2), resolve_radix(None, Some(2)));
+    assert_eq!(
+        Err("--radix 10 does not match the policy's radix 16".to_string()),
+        resolve_radix(Some(16), Some(10))
+    );
+}
+
+#[test]
+fn test_explain_improved_password() {
+    let violations = explain_improved_password("123444", 10).unwrap();
+    assert_eq!(1, violations.len());
+    assert_eq!("no run of exactly 2", violations[0].to_string());
+    assert_eq!(Ok(vec![]), explain_improved_password("112233", 10));
+    assert_eq!(Some(3), explain_password("11210", 10).unwrap()[0].position);
+    assert!(explain_password("12z", 10).is_err());
 }
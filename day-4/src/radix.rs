@@ -0,0 +1,108 @@
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub enum PasswordError {
+    InvalidRadix(u32),
+    InvalidDigit {
+        position: usize,
+        chr: char,
+        radix: u32,
+    },
+    Empty,
+}
+
+impl fmt::Display for PasswordError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PasswordError::InvalidRadix(radix) => {
+                write!(f, "radix {} is not between 2 and 36", radix)
+            }
+            PasswordError::InvalidDigit {
+                position,
+                chr,
+                radix,
+            } => write!(
+                f,
+                "digit {} '{}' is not a base {} digit",
+                position + 1,
+                chr,
+                radix
+            ),
+            PasswordError::Empty => write!(f, "password is empty"),
+        }
+    }
+}
+
+pub fn check_radix(radix: u32) -> Result<(), PasswordError> {
+    match radix {
+        2..=36 => Ok(()),
+        _ => Err(PasswordError::InvalidRadix(radix)),
+    }
+}
+
+/// Digit values of a password, most significant first.
+pub fn parse_digits(password: &str, radix: u32) -> Result<Vec<u8>, PasswordError> {
+    check_radix(radix)?;
+    if password.is_empty() {
+        return Err(PasswordError::Empty);
+    }
+    password
+        .chars()
+        .enumerate()
+        .map(|(position, chr)| match chr.to_digit(radix) {
+            Some(digit) => Ok(digit as u8),
+            None => Err(PasswordError::InvalidDigit {
+                position,
+                chr,
+                radix,
+            }),
+        })
+        .collect()
+}
+
+/// Digit values of `n` written in `radix`, most significant first.
+pub fn to_digits(mut n: u128, radix: u32) -> Vec<u8> {
+    let mut digits: Vec<u8> = vec![];
+    loop {
+        digits.push((n % u128::from(radix)) as u8);
+        n /= u128::from(radix);
+        if n == 0 {
+            break;
+        }
+    }
+    digits.reverse();
+    digits
+}
+
+pub fn format_in_radix(n: u128, radix: u32) -> String {
+    to_digits(n, radix)
+        .iter()
+        .map(|&digit| std::char::from_digit(u32::from(digit), radix).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_parse_digits() {
+    assert_eq!(Ok(vec![1, 10, 15]), parse_digits("1aF", 16));
+    assert_eq!(
+        Err(PasswordError::InvalidDigit {
+            position: 2,
+            chr: '2',
+            radix: 2
+        }),
+        parse_digits("112", 2)
+    );
+    assert_eq!(Err(PasswordError::InvalidRadix(37)), parse_digits("1", 37));
+    assert_eq!(Err(PasswordError::Empty), parse_digits("", 10));
+}
+
+#[test]
+fn test_format_in_radix() {
+    assert_eq!("0", format_in_radix(0, 10));
+    assert_eq!("ff", format_in_radix(255, 16));
+    assert_eq!("1011", format_in_radix(11, 2));
+    assert_eq!(
+        "340282366920938463463374607431768211455",
+        format_in_radix(u128::MAX, 10)
+    );
+}
@@ -0,0 +1,526 @@
+use std::fmt;
+
+use crate::radix::format_in_radix;
+
+/// Why a password failed a rule. `position` is the index of the offending
+/// character when the failure can be pinned to one.
+#[derive(Debug, PartialEq)]
+pub struct Violation {
+    pub position: Option<usize>,
+    pub reason: String,
+}
+
+impl Violation {
+    fn at(position: usize, reason: String) -> Violation {
+        Violation {
+            position: Some(position),
+            reason,
+        }
+    }
+
+    fn anywhere(reason: String) -> Violation {
+        Violation {
+            position: None,
+            reason,
+        }
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.reason)
+    }
+}
+
+/// A single condition a password has to fulfil. Rules can be combined with
+/// `and`, `or` and `not`, or collected into a `RuleSet`.
+pub trait PasswordRule {
+    /// Short description of what the rule requires, e.g. "a run of exactly 2".
+    fn describe(&self) -> String;
+
+    fn explain(&self, password: &str) -> Result<(), Violation>;
+
+    fn check(&self, password: &str) -> bool {
+        self.explain(password).is_ok()
+    }
+
+    fn and<R: PasswordRule>(self, other: R) -> And<Self, R>
+    where
+        Self: Sized,
+    {
+        And(self, other)
+    }
+
+    fn or<R: PasswordRule>(self, other: R) -> Or<Self, R>
+    where
+        Self: Sized,
+    {
+        Or(self, other)
+    }
+
+    fn not(self) -> Not<Self>
+    where
+        Self: Sized,
+    {
+        Not(self)
+    }
+
+    /// Numbers in the range, written in `radix`, that pass the rule. They are
+    /// checked lazily, one at a time as the iterator is advanced.
+    fn matching_in_range(
+        &self,
+        lower_limit: u128,
+        upper_limit: u128,
+        radix: u32,
+    ) -> impl Iterator<Item = String> + '_
+    where
+        Self: Sized,
+    {
+        (lower_limit..=upper_limit)
+            .map(move |x| format_in_radix(x, radix))
+            .filter(move |password| self.check(password))
+    }
+
+    fn count_in_range(&self, lower_limit: u128, upper_limit: u128, radix: u32) -> usize {
+        (lower_limit..=upper_limit)
+            .filter(|&x| self.check(&format_in_radix(x, radix)))
+            .count()
+    }
+}
+
+pub struct Length(pub usize);
+
+/// Every character is a digit in the given radix.
+pub struct DigitsOnly(pub u32);
+
+/// Every character is one of the given ones.
+pub struct Alphabet(pub String);
+
+pub struct NonDecreasing;
+
+pub struct NonIncreasing;
+
+pub struct HasAdjacentPair;
+
+/// Some run of equal characters is exactly this long.
+pub struct HasRunOfLength(pub usize);
+
+/// Some run of equal characters is at least this long.
+pub struct HasRunOfAtLeast(pub usize);
+
+/// No run of equal characters is longer than this.
+pub struct MaxRunLength(pub usize);
+
+/// The password read as a number in `radix` lies in the range.
+pub struct WithinRange {
+    pub lower_limit: u128,
+    pub upper_limit: u128,
+    pub radix: u32,
+}
+
+pub struct And<A, B>(A, B);
+
+pub struct Or<A, B>(A, B);
+
+pub struct Not<A>(A);
+
+/// Passes when every contained rule passes.
+#[derive(Default)]
+pub struct RuleSet {
+    rules: Vec<Box<dyn PasswordRule>>,
+}
+
+impl RuleSet {
+    pub fn new() -> RuleSet {
+        RuleSet::default()
+    }
+
+    pub fn with<R: PasswordRule + 'static>(mut self, rule: R) -> RuleSet {
+        self.rules.push(Box::new(rule));
+        self
+    }
+}
+
+/// Letter digits compare the same regardless of case, so `"aB"` is increasing.
+fn normalized_chars(password: &str) -> Vec<char> {
+    password
+        .chars()
+        .map(|chr| chr.to_ascii_lowercase())
+        .collect()
+}
+
+fn run_lengths(password: &str) -> Vec<usize> {
+    let mut runs: Vec<usize> = vec![];
+    let mut last_char = None;
+    for chr in normalized_chars(password) {
+        if Some(chr) == last_char {
+            *runs.last_mut().unwrap() += 1;
+        } else {
+            runs.push(1);
+        }
+        last_char = Some(chr);
+    }
+    runs
+}
+
+/// First position whose character is not accepted by `is_valid`.
+fn first_invalid(password: &str, is_valid: impl Fn(char) -> bool) -> Option<(usize, char)> {
+    password
+        .chars()
+        .enumerate()
+        .find(|&(_, chr)| !is_valid(chr))
+}
+
+/// First position that breaks the ordering between neighbouring characters.
+fn first_out_of_order(password: &str, in_order: impl Fn(char, char) -> bool) -> Option<usize> {
+    normalized_chars(password)
+        .windows(2)
+        .position(|pair| !in_order(pair[0], pair[1]))
+        .map(|pos| pos + 1)
+}
+
+impl PasswordRule for Length {
+    fn describe(&self) -> String {
+        format!("length {}", self.0)
+    }
+
+    fn explain(&self, password: &str) -> Result<(), Violation> {
+        match password.chars().count() {
+            len if len == self.0 => Ok(()),
+            len => Err(Violation::anywhere(format!(
+                "length is {}, expected {}",
+                len, self.0
+            ))),
+        }
+    }
+}
+
+impl PasswordRule for DigitsOnly {
+    fn describe(&self) -> String {
+        format!("only base {} digits", self.0)
+    }
+
+    fn explain(&self, password: &str) -> Result<(), Violation> {
+        match first_invalid(password, |chr| chr.is_digit(self.0)) {
+            Some((pos, chr)) => Err(Violation::at(
+                pos,
+                format!("digit {} '{}' is not a base {} digit", pos + 1, chr, self.0),
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+impl PasswordRule for Alphabet {
+    fn describe(&self) -> String {
+        format!("only characters from \"{}\"", self.0)
+    }
+
+    fn explain(&self, password: &str) -> Result<(), Violation> {
+        match first_invalid(password, |chr| self.0.contains(chr)) {
+            Some((pos, chr)) => Err(Violation::at(
+                pos,
+                format!("digit {} '{}' is not in the alphabet", pos + 1, chr),
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+impl PasswordRule for NonDecreasing {
+    fn describe(&self) -> String {
+        "non-decreasing digits".to_string()
+    }
+
+    fn explain(&self, password: &str) -> Result<(), Violation> {
+        match first_out_of_order(password, |a, b| a <= b) {
+            Some(pos) => Err(Violation::at(pos, format!("digit {} decreases", pos + 1))),
+            None => Ok(()),
+        }
+    }
+}
+
+impl PasswordRule for NonIncreasing {
+    fn describe(&self) -> String {
+        "non-increasing digits".to_string()
+    }
+
+    fn explain(&self, password: &str) -> Result<(), Violation> {
+        match first_out_of_order(password, |a, b| a >= b) {
+            Some(pos) => Err(Violation::at(pos, format!("digit {} increases", pos + 1))),
+            None => Ok(()),
+        }
+    }
+}
+
+impl PasswordRule for HasAdjacentPair {
+    fn describe(&self) -> String {
+        "two adjacent matching digits".to_string()
+    }
+
+    fn explain(&self, password: &str) -> Result<(), Violation> {
+        if run_lengths(password).iter().any(|&len| len >= 2) {
+            return Ok(());
+        }
+        Err(Violation::anywhere(
+            "no two adjacent digits match".to_string(),
+        ))
+    }
+}
+
+impl PasswordRule for HasRunOfLength {
+    fn describe(&self) -> String {
+        format!("a run of exactly {}", self.0)
+    }
+
+    fn explain(&self, password: &str) -> Result<(), Violation> {
+        if run_lengths(password).contains(&self.0) {
+            return Ok(());
+        }
+        Err(Violation::anywhere(format!("no run of exactly {}", self.0)))
+    }
+}
+
+impl PasswordRule for HasRunOfAtLeast {
+    fn describe(&self) -> String {
+        format!("a run of at least {}", self.0)
+    }
+
+    fn explain(&self, password: &str) -> Result<(), Violation> {
+        if run_lengths(password).iter().any(|&len| len >= self.0) {
+            return Ok(());
+        }
+        Err(Violation::anywhere(format!(
+            "no run of at least {}",
+            self.0
+        )))
+    }
+}
+
+impl PasswordRule for MaxRunLength {
+    fn describe(&self) -> String {
+        format!("no run longer than {}", self.0)
+    }
+
+    fn explain(&self, password: &str) -> Result<(), Violation> {
+        let mut pos = 0;
+        for len in run_lengths(password) {
+            if len > self.0 {
+                return Err(Violation::at(
+                    pos,
+                    format!(
+                        "run of {} starting at digit {} is longer than {}",
+                        len,
+                        pos + 1,
+                        self.0
+                    ),
+                ));
+            }
+            pos += len;
+        }
+        Ok(())
+    }
+}
+
+impl PasswordRule for WithinRange {
+    fn describe(&self) -> String {
+        format!(
+            "between {} and {}",
+            format_in_radix(self.lower_limit, self.radix),
+            format_in_radix(self.upper_limit, self.radix)
+        )
+    }
+
+    fn explain(&self, password: &str) -> Result<(), Violation> {
+        match u128::from_str_radix(password, self.radix) {
+            Ok(x) if (self.lower_limit..=self.upper_limit).contains(&x) => Ok(()),
+            _ => Err(Violation::anywhere(format!("not {}", self.describe()))),
+        }
+    }
+}
+
+impl<A: PasswordRule, B: PasswordRule> PasswordRule for And<A, B> {
+    fn describe(&self) -> String {
+        format!("{} and {}", self.0.describe(), self.1.describe())
+    }
+
+    fn explain(&self, password: &str) -> Result<(), Violation> {
+        self.0.explain(password)?;
+        self.1.explain(password)
+    }
+}
+
+impl<A: PasswordRule, B: PasswordRule> PasswordRule for Or<A, B> {
+    fn describe(&self) -> String {
+        format!("{} or {}", self.0.describe(), self.1.describe())
+    }
+
+    fn explain(&self, password: &str) -> Result<(), Violation> {
+        match (self.0.explain(password), self.1.explain(password)) {
+            (Err(first), Err(second)) => {
+                Err(Violation::anywhere(format!("{}, and {}", first, second)))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<A: PasswordRule> PasswordRule for Not<A> {
+    fn describe(&self) -> String {
+        format!("not {}", self.0.describe())
+    }
+
+    fn explain(&self, password: &str) -> Result<(), Violation> {
+        match self.0.explain(password) {
+            Ok(()) => Err(Violation::anywhere(format!(
+                "must not have {}",
+                self.0.describe()
+            ))),
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+impl RuleSet {
+    /// Every violated rule, in the order the rules were added.
+    pub fn violations(&self, password: &str) -> Vec<Violation> {
+        self.rules
+            .iter()
+            .filter_map(|rule| rule.explain(password).err())
+            .collect()
+    }
+}
+
+impl PasswordRule for RuleSet {
+    fn describe(&self) -> String {
+        let descriptions: Vec<String> = self.rules.iter().map(|rule| rule.describe()).collect();
+        descriptions.join(", ")
+    }
+
+    fn explain(&self, password: &str) -> Result<(), Violation> {
+        for rule in self.rules.iter() {
+            rule.explain(password)?;
+        }
+        Ok(())
+    }
+}
+
+impl PasswordRule for Box<dyn PasswordRule> {
+    fn describe(&self) -> String {
+        (**self).describe()
+    }
+
+    fn explain(&self, password: &str) -> Result<(), Violation> {
+        (**self).explain(password)
+    }
+}
+
+pub fn password_rules(radix: u32) -> RuleSet {
+    RuleSet::new()
+        .with(DigitsOnly(radix))
+        .with(NonDecreasing)
+        .with(HasAdjacentPair)
+}
+
+pub fn improved_password_rules(radix: u32) -> RuleSet {
+    RuleSet::new()
+        .with(DigitsOnly(radix))
+        .with(NonDecreasing)
+        .with(HasRunOfLength(2))
+}
+
+/// The complete puzzle policy, including the six digit length and the input range.
+#[cfg(test)]
+pub fn puzzle_rules(lower_limit: u128, upper_limit: u128) -> RuleSet {
+    improved_password_rules(10)
+        .with(Length(6))
+        .with(WithinRange {
+            lower_limit,
+            upper_limit,
+            radix: 10,
+        })
+}
+
+#[test]
+fn test_built_in_rules() {
+    assert!(Length(6).check("123456"));
+    assert!(!DigitsOnly(10).check("12a456"));
+    assert!(DigitsOnly(16).check("12a456"));
+    assert!(!NonDecreasing.check("223450"));
+    assert!(HasAdjacentPair.check("123444"));
+    assert!(!HasRunOfLength(2).check("123444"));
+    assert!(HasRunOfLength(3).check("123444"));
+    assert!(HasRunOfAtLeast(3).check("123444"));
+    assert!(!MaxRunLength(2).check("123444"));
+    assert!(NonIncreasing.check("985530"));
+    assert!(!Alphabet("13579".to_string()).check("13570"));
+    assert!(!WithinRange {
+        lower_limit: 10,
+        upper_limit: 20,
+        radix: 10
+    }
+    .check("21"));
+    assert!(WithinRange {
+        lower_limit: 10,
+        upper_limit: 20,
+        radix: 16
+    }
+    .check("14"));
+}
+
+#[test]
+fn test_combinators() {
+    let no_triples = HasAdjacentPair.and(HasRunOfLength(3).not());
+    assert!(no_triples.check("112345"));
+    assert!(!no_triples.check("111345"));
+
+    let short_or_sorted = Length(2).or(NonDecreasing);
+    assert!(short_or_sorted.check("91"));
+    assert!(short_or_sorted.check("129"));
+    assert!(!short_or_sorted.check("921"));
+}
+
+#[test]
+fn test_puzzle_rules() {
+    let rules = puzzle_rules(156_218, 652_527);
+    assert!(rules.check("223345"));
+    assert!(!rules.check("111122"));
+    assert_eq!(1148, rules.count_in_range(156_218, 652_527, 10));
+    assert_eq!(
+        vec!["111122"],
+        improved_password_rules(10)
+            .matching_in_range(111_111, 111_122, 10)
+            .collect::<Vec<String>>()
+    );
+    assert!(improved_password_rules(16).check("1aAbf"));
+}
+
+#[test]
+fn test_explain() {
+    assert_eq!(
+        Err(Violation::anywhere("no run of exactly 2".to_string())),
+        improved_password_rules(10).explain("123444")
+    );
+    assert_eq!(
+        Err(Violation::at(5, "digit 6 decreases".to_string())),
+        NonDecreasing.explain("223450")
+    );
+    assert_eq!(
+        Err(Violation::anywhere(
+            "must not have a run of exactly 3".to_string()
+        )),
+        HasRunOfLength(3).not().explain("123444")
+    );
+    assert_eq!(
+        vec![
+            "digit 7 decreases",
+            "no run of exactly 2",
+            "length is 7, expected 6"
+        ],
+        puzzle_rules(0, 9_999_999)
+            .violations("1234440")
+            .iter()
+            .map(|violation| violation.to_string())
+            .collect::<Vec<String>>()
+    );
+}
@@ -0,0 +1,128 @@
+//! Property tests checking day-5's interpreter against `intcode::Machine`:
+//! both decode instructions the same way, and random programs end with the
+//! same memory and outputs.
+
+use proptest::collection::vec;
+use proptest::prelude::*;
+use proptest::sample::{select, Index};
+
+use intcode::{decode, Machine, Mode, Opcode, Status};
+
+use crate::{get_operator, get_outputs, Instruction, ParameterMode};
+
+/// An opcode day-5 knows with position or immediate modes on all three
+/// parameters, whether the opcode uses them or not.
+fn instruction() -> impl Strategy<Value = i64> {
+    (prop_oneof![(1_i64..=8), Just(99)], vec(0_i64..2, 3)).prop_map(|(opcode, modes)| {
+        modes
+            .iter()
+            .zip([100, 1000, 10000].iter())
+            .fold(opcode, |value, (mode, place)| value + mode * place)
+    })
+}
+
+/// The choices for one instruction of `program`: the opcode, whether its two
+/// input parameters are immediate, three raw parameters and where a jump
+/// lands.
+type Choice = (i64, bool, bool, i64, i64, usize, Index);
+
+fn choice() -> impl Strategy<Value = Choice> {
+    (
+        select(&[1_i64, 2, 4, 5, 6, 7, 8][..]),
+        any::<bool>(),
+        any::<bool>(),
+        0_i64..10,
+        0_i64..10,
+        0_usize..8,
+        any::<Index>(),
+    )
+}
+
+/// A program day-5 can run without reading stdin, looping or overflowing:
+/// only forward jumps, outputs in position mode, writes into a data area
+/// after the `hlt` and small multipliers.
+fn program() -> impl Strategy<Value = Vec<i64>> {
+    (vec(choice(), 1..9), vec(0_i64..10, 8)).prop_map(|(choices, data)| {
+        let lengths: Vec<usize> = choices
+            .iter()
+            .map(|&(opcode, ..)| match opcode {
+                4 => 2,
+                5 | 6 => 3,
+                _ => 4,
+            })
+            .collect();
+        let data_start = lengths.iter().sum::<usize>() + 1;
+        let operand = |immediate: bool, value: i64| {
+            if immediate {
+                value
+            } else {
+                (data_start + value as usize % 8) as i64
+            }
+        };
+
+        let mut program = vec![];
+        for (n, &(opcode, left, right, a, b, c, jump)) in choices.iter().enumerate() {
+            let start = program.len();
+            program.push(opcode);
+            match opcode {
+                4 => program.push(operand(false, a)),
+                5 | 6 => {
+                    program.push(operand(left, a));
+                    let later = n + 1 + jump.index(choices.len() - n);
+                    program.push((start + lengths[n..later].iter().sum::<usize>()) as i64);
+                    program[start] += 1000 + 100 * left as i64;
+                }
+                _ => {
+                    let left = opcode == 2 || left;
+                    program.push(if opcode == 2 { a % 4 } else { operand(left, a) });
+                    program.push(operand(right, b));
+                    program.push(operand(false, c as i64));
+                    program[start] += 100 * left as i64 + 1000 * right as i64;
+                }
+            }
+        }
+        program.push(99);
+        program.extend(data);
+        program
+    })
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(2000))]
+
+    #[test]
+    fn test_get_operator_matches_decode(value in instruction()) {
+        let operator = get_operator(value);
+        let decoded = decode(value, 0).unwrap();
+        let instruction = match decoded.opcode {
+            Opcode::Add => Instruction::Add,
+            Opcode::Multiply => Instruction::Multiply,
+            Opcode::Input => Instruction::Input,
+            Opcode::Output => Instruction::Output,
+            Opcode::JumpIfTrue => Instruction::JumpIfTrue,
+            Opcode::JumpIfFalse => Instruction::JumpIfFalse,
+            Opcode::LessThan => Instruction::LessThan,
+            Opcode::Equals => Instruction::Equals,
+            Opcode::AdjustRelativeBase => Instruction::Unknown,
+            Opcode::Halt => Instruction::Halt,
+        };
+        let mode = |mode: Mode| match mode {
+            Mode::Immediate => ParameterMode::Immediate,
+            _ => ParameterMode::Position,
+        };
+        prop_assert_eq!(instruction, operator.instruction);
+        prop_assert_eq!(mode(decoded.modes[0]), operator.param1);
+        prop_assert_eq!(mode(decoded.modes[1]), operator.param2);
+        prop_assert_eq!(mode(decoded.modes[2]), operator.param3);
+    }
+
+    #[test]
+    fn test_random_programs_match_machine(program in program()) {
+        let mut expected = program.clone();
+        let outputs = get_outputs(&mut expected).unwrap();
+        let mut machine = Machine::new(program);
+        prop_assert_eq!(Ok(Status::Halted), machine.run());
+        prop_assert_eq!(&expected[..], machine.memory());
+        prop_assert_eq!(outputs, machine.take_output());
+    }
+}
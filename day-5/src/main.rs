@@ -1,7 +1,13 @@
+use std::collections::VecDeque;
 use std::env;
 use std::fs;
-use std::io;
-use std::iter::FromIterator;
+use std::io::{self, Write};
+use std::process;
+
+use intcode::{AsciiMachine, Format, Limits, Machine, MachineError, Status};
+
+#[cfg(test)]
+mod fuzz;
 
 #[derive(PartialEq, Debug)]
 enum ParameterMode {
@@ -41,7 +47,7 @@ impl ParameterMode {
 }
 
 impl Instruction {
-    fn from_i32(x: i32) -> Instruction {
+    fn from_i64(x: i64) -> Instruction {
         match x {
             1 => Instruction::Add,
             2 => Instruction::Multiply,
@@ -57,32 +63,32 @@ impl Instruction {
     }
 }
 
-fn get_operator(op_codes: i32) -> Operator {
+fn get_operator(op_codes: i64) -> Operator {
     let chars: Vec<char> = op_codes.to_string().chars().collect();
 
     let op_code = match chars.len() {
-        2..=1000 => Instruction::from_i32(
-            i32::from_str_radix(
-                &String::from_iter(chars[chars.len() - 2 as usize..].iter()),
-                10,
-            )
-            .unwrap(),
+        2..=1000 => Instruction::from_i64(
+            chars[chars.len() - 2..]
+                .iter()
+                .collect::<String>()
+                .parse()
+                .unwrap(),
         ),
-        _ => Instruction::from_i32(chars[chars.len() - 1 as usize].to_digit(10).unwrap() as i32),
+        _ => Instruction::from_i64(i64::from(chars[chars.len() - 1].to_digit(10).unwrap())),
     };
 
     let param1_mode = match chars.len() {
-        3..=1000 => ParameterMode::from_char(chars[chars.len() - 3 as usize]),
+        3..=1000 => ParameterMode::from_char(chars[chars.len() - 3]),
         _ => ParameterMode::Position,
     };
 
     let param2_mode = match chars.len() {
-        4..=1000 => ParameterMode::from_char(chars[chars.len() - 4 as usize]),
+        4..=1000 => ParameterMode::from_char(chars[chars.len() - 4]),
         _ => ParameterMode::Position,
     };
 
     let param3_mode = match chars.len() {
-        5..=1000 => ParameterMode::from_char(chars[chars.len() - 5 as usize]),
+        5..=1000 => ParameterMode::from_char(chars[chars.len() - 5]),
         _ => ParameterMode::Position,
     };
 
@@ -94,37 +100,45 @@ fn get_operator(op_codes: i32) -> Operator {
     }
 }
 
-fn get_mutated_sequence(v: &mut Vec<i32>) -> &mut Vec<i32> {
-    let mut curr_op_code = get_operator(v[0]);
+fn get_mutated_sequence(v: &mut [i64]) -> Result<&mut [i64], MachineError> {
+    get_outputs(v)?;
+    Ok(v)
+}
+
+/// Runs the program in place and returns everything it output. Unknown
+/// opcodes, including running off the end of the program, fail the same way
+/// they do in `intcode::Machine`.
+fn get_outputs(v: &mut [i64]) -> Result<Vec<i64>, MachineError> {
+    let mut outputs = vec![];
+    let read = |v: &[i64], pos: usize| v.get(pos).copied().unwrap_or(0);
+    let mut curr_op_code = get_operator(read(v, 0));
     let mut current_pos = 0;
 
     while curr_op_code.instruction != Instruction::Halt {
-        let mut next_pos: usize = current_pos;
-        match curr_op_code.instruction {
-            Instruction::Add => next_pos = add_instruction(v, current_pos, &curr_op_code),
-            Instruction::Multiply => next_pos = mult_instruction(v, current_pos, &curr_op_code),
-            Instruction::Input => next_pos = input_instruction(v, current_pos),
-            Instruction::Output => next_pos = output_instruction(v, current_pos),
-            Instruction::JumpIfTrue => {
-                next_pos = jump_if_true_instruction(v, current_pos, &curr_op_code)
+        let next_pos = match curr_op_code.instruction {
+            Instruction::Add => add_instruction(v, current_pos, &curr_op_code),
+            Instruction::Multiply => mult_instruction(v, current_pos, &curr_op_code),
+            Instruction::Input => input_instruction(v, current_pos),
+            Instruction::Output => output_instruction(v, current_pos, &mut outputs),
+            Instruction::JumpIfTrue => jump_if_true_instruction(v, current_pos, &curr_op_code),
+            Instruction::JumpIfFalse => jump_if_false_instruction(v, current_pos, &curr_op_code),
+            Instruction::LessThan => less_than_instruction(v, current_pos, &curr_op_code),
+            Instruction::Equals => equals_instruction(v, current_pos, &curr_op_code),
+            _ => {
+                return Err(MachineError::UnknownOpcode {
+                    ip: current_pos,
+                    value: read(v, current_pos),
+                })
             }
-            Instruction::JumpIfFalse => {
-                next_pos = jump_if_false_instruction(v, current_pos, &curr_op_code)
-            }
-            Instruction::LessThan => {
-                next_pos = less_than_instruction(v, current_pos, &curr_op_code)
-            }
-            Instruction::Equals => next_pos = equals_instruction(v, current_pos, &curr_op_code),
-            _ => println!("unknown op code {:?}", curr_op_code.instruction),
-        }
+        };
         current_pos = next_pos;
-        curr_op_code = get_operator(v[current_pos]);
+        curr_op_code = get_operator(read(v, current_pos));
     }
 
-    v
+    Ok(outputs)
 }
 
-fn add_instruction(v: &mut Vec<i32>, current_pos: usize, operator: &Operator) -> usize {
+fn add_instruction(v: &mut [i64], current_pos: usize, operator: &Operator) -> usize {
     let l_value = match operator.param1 {
         ParameterMode::Position => v[v[current_pos + 1] as usize],
         ParameterMode::Immediate => v[current_pos + 1],
@@ -142,7 +156,7 @@ fn add_instruction(v: &mut Vec<i32>, current_pos: usize, operator: &Operator) ->
     current_pos + 4
 }
 
-fn mult_instruction(v: &mut Vec<i32>, current_pos: usize, operator: &Operator) -> usize {
+fn mult_instruction(v: &mut [i64], current_pos: usize, operator: &Operator) -> usize {
     let l_value = match operator.param1 {
         ParameterMode::Position => v[v[current_pos + 1] as usize],
         ParameterMode::Immediate => v[current_pos + 1],
@@ -160,21 +174,22 @@ fn mult_instruction(v: &mut Vec<i32>, current_pos: usize, operator: &Operator) -
     current_pos + 4
 }
 
-fn input_instruction(v: &mut Vec<i32>, current_pos: usize) -> usize {
+fn read_input() -> i64 {
     println!("Input integer: ");
     let mut input = String::new();
-    let output_pos = v[current_pos + 1] as usize;
     match io::stdin().read_line(&mut input) {
-        Ok(_) => {
-            let input = i32::from_str_radix(&input.trim(), 10).unwrap();
-            v[output_pos] = input;
-        }
-        Err(error) => panic!(error),
+        Ok(_) => input.trim().parse().unwrap(),
+        Err(error) => panic!("{}", error),
     }
+}
+
+fn input_instruction(v: &mut [i64], current_pos: usize) -> usize {
+    let output_pos = v[current_pos + 1] as usize;
+    v[output_pos] = read_input();
     current_pos + 2
 }
 
-fn jump_if_true_instruction(v: &[i32], current_pos: usize, operator: &Operator) -> usize {
+fn jump_if_true_instruction(v: &[i64], current_pos: usize, operator: &Operator) -> usize {
     let l_value = match operator.param1 {
         ParameterMode::Position => v[v[current_pos + 1] as usize],
         ParameterMode::Immediate => v[current_pos + 1],
@@ -191,7 +206,7 @@ fn jump_if_true_instruction(v: &[i32], current_pos: usize, operator: &Operator)
     current_pos + 3
 }
 
-fn jump_if_false_instruction(v: &[i32], current_pos: usize, operator: &Operator) -> usize {
+fn jump_if_false_instruction(v: &[i64], current_pos: usize, operator: &Operator) -> usize {
     let l_value = match operator.param1 {
         ParameterMode::Position => v[v[current_pos + 1] as usize],
         ParameterMode::Immediate => v[current_pos + 1],
@@ -208,7 +223,7 @@ fn jump_if_false_instruction(v: &[i32], current_pos: usize, operator: &Operator)
     current_pos + 3
 }
 
-fn less_than_instruction(v: &mut Vec<i32>, current_pos: usize, operator: &Operator) -> usize {
+fn less_than_instruction(v: &mut [i64], current_pos: usize, operator: &Operator) -> usize {
     let l_value = match operator.param1 {
         ParameterMode::Position => v[v[current_pos + 1] as usize],
         ParameterMode::Immediate => v[current_pos + 1],
@@ -229,7 +244,7 @@ fn less_than_instruction(v: &mut Vec<i32>, current_pos: usize, operator: &Operat
     current_pos + 4
 }
 
-fn equals_instruction(v: &mut Vec<i32>, current_pos: usize, operator: &Operator) -> usize {
+fn equals_instruction(v: &mut [i64], current_pos: usize, operator: &Operator) -> usize {
     let l_value = match operator.param1 {
         ParameterMode::Position => v[v[current_pos + 1] as usize],
         ParameterMode::Immediate => v[current_pos + 1],
@@ -250,37 +265,218 @@ fn equals_instruction(v: &mut Vec<i32>, current_pos: usize, operator: &Operator)
     current_pos + 4
 }
 
-fn output_instruction(v: &[i32], current_pos: usize) -> usize {
-    if v[v[current_pos + 1] as usize] > 0 {
-        println!(
-            "Possible error: {} (current pos: {})",
-            v[v[current_pos + 1] as usize],
-            current_pos
-        );
-    } else {
-        println!("Output: {}", v[v[current_pos + 1] as usize]);
+fn output_instruction(v: &[i64], current_pos: usize, outputs: &mut Vec<i64>) -> usize {
+    let value = v[v[current_pos + 1] as usize];
+    println!("Output: {}", value);
+    outputs.push(value);
+    current_pos + 2
+}
+
+struct Options {
+    inputs: Option<Vec<i64>>,
+    expected: Option<Vec<i64>>,
+    ascii: bool,
+    diagnostic: Option<i64>,
+    checkpoint: Option<String>,
+    after: Option<u64>,
+    limits: Limits,
+}
+
+fn parse_values(text: &str) -> Vec<i64> {
+    intcode::parse_text(text).unwrap_or_else(|error| panic!("{}", error))
+}
+
+fn read_values(filename: &str) -> Vec<i64> {
+    parse_values(&fs::read_to_string(filename).expect("Could not open values file"))
+}
+
+/// Values for `--inputs` and `--expect` are comma or whitespace separated, the
+/// `-file` variants read the same format from a file.
+fn parse_options(args: &[String]) -> Options {
+    let mut inputs = None;
+    let mut expected = None;
+    let mut ascii = false;
+    let mut diagnostic = None;
+    let mut checkpoint = None;
+    let mut after = None;
+    let mut limits = Limits::default();
+    let mut options = args.iter();
+    while let Some(option) = options.next() {
+        let mut value = || options.next().expect("Option needs a value").as_str();
+        match option.as_str() {
+            "--inputs" => inputs = Some(parse_values(value())),
+            "--inputs-file" => inputs = Some(read_values(value())),
+            "--expect" => expected = Some(parse_values(value())),
+            "--expect-file" => expected = Some(read_values(value())),
+            "--ascii" => ascii = true,
+            "--diagnostic" => {
+                diagnostic = Some(value().parse().expect("Expected a system ID"));
+            }
+            "--checkpoint" => checkpoint = Some(value().to_string()),
+            "--after" => after = Some(value().parse().expect("Expected a number of steps")),
+            "--max-steps" => limits.max_steps = Some(value().parse().expect("Expected a limit")),
+            "--max-memory" => limits.max_memory = Some(value().parse().expect("Expected a limit")),
+            "--max-outputs" => {
+                limits.max_outputs = Some(value().parse().expect("Expected a limit"))
+            }
+            _ => panic!("Unknown option {}", option),
+        }
     }
+    if checkpoint.is_some() != after.is_some() {
+        panic!("--checkpoint and --after go together");
+    }
+    Options {
+        inputs,
+        expected,
+        ascii,
+        diagnostic,
+        checkpoint,
+        after,
+        limits,
+    }
+}
 
-    current_pos + 2
+/// Runs a machine, taking input from the scripted inputs if there are any and
+/// from stdin otherwise, and returns every output. With a checkpoint it stops
+/// after that many steps and saves a snapshot which `resume` continues from,
+/// returning `None`.
+fn run_machine(mut machine: Machine, options: &Options) -> Option<Vec<i64>> {
+    let mut inputs: Option<VecDeque<i64>> = options.inputs.clone().map(VecDeque::from);
+    let mut outputs = vec![];
+    let mut steps = 0;
+    loop {
+        if let (Some(path), Some(after)) = (&options.checkpoint, options.after) {
+            if steps == after {
+                machine
+                    .save_snapshot(path)
+                    .unwrap_or_else(|error| panic!("{}", error));
+                println!("Saved snapshot after {} steps to {}", steps, path);
+                return None;
+            }
+        }
+        match machine.step().unwrap_or_else(|error| panic!("{}", error)) {
+            Status::Running => steps += 1,
+            Status::NeedsInput => match inputs.as_mut() {
+                Some(inputs) => match inputs.pop_front() {
+                    Some(value) => machine.push_input(value),
+                    None => panic!("Program needs more input than was supplied"),
+                },
+                None => machine.push_input(read_input()),
+            },
+            Status::Halted => return Some(outputs),
+        }
+        while let Some(value) = machine.pop_output() {
+            println!("Output: {}", value);
+            outputs.push(value);
+        }
+    }
+}
+
+/// One line per position where the outputs differ from the expected ones.
+fn diff_outputs(expected: &[i64], actual: &[i64]) -> Vec<String> {
+    let describe = |value: Option<&i64>| match value {
+        Some(value) => value.to_string(),
+        None => "nothing".to_string(),
+    };
+    (0..expected.len().max(actual.len()))
+        .filter(|&n| expected.get(n) != actual.get(n))
+        .map(|n| {
+            format!(
+                "output {}: expected {}, got {}",
+                n + 1,
+                describe(expected.get(n)),
+                describe(actual.get(n))
+            )
+        })
+        .collect()
+}
+
+/// Runs a program that speaks ASCII, sending each line of stdin as text and
+/// printing its output as text until it halts or stdin ends.
+fn run_ascii(machine: Machine) {
+    let mut machine = AsciiMachine::new(machine);
+    loop {
+        let (status, chunks) = machine.run().unwrap_or_else(|error| panic!("{}", error));
+        for chunk in chunks {
+            print!("{}", chunk);
+        }
+        io::stdout().flush().unwrap();
+        if status == Status::Halted {
+            return;
+        }
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line).unwrap() == 0 {
+            return;
+        }
+        machine.send_line(line.trim_end_matches(['\n', '\r']));
+    }
 }
 
 fn main() {
     let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
+    if args.len() > 1 && args[1] == "convert" {
+        if args.len() != 5 {
+            panic!("Usage: day-5 convert <input> <output> <comma|whitespace|binary>");
+        }
+        let format = Format::from_name(&args[4]).expect("Unknown program format");
+        let program = intcode::load_program(&args[2]).unwrap_or_else(|error| panic!("{}", error));
+        intcode::save_program(&args[3], &program, format)
+            .unwrap_or_else(|error| panic!("{}", error));
+        return;
+    }
+    if args.len() < 2 {
         panic!("No input file supplied!");
     }
-    let filename = &args[1];
-    println!("input file is: {}", filename);
-    let file_content = fs::read_to_string(filename).expect("Could not open input file");
 
-    let input_sequence: Vec<i32> = file_content
-        .split(',')
-        .map(|x| i32::from_str_radix(x, 10).unwrap())
-        .collect();
-
-    println!("{:?}", input_sequence);
+    let (machine, options) = if args[1] == "resume" {
+        let snapshot = args.get(2).expect("No snapshot file supplied!");
+        let machine = Machine::load_snapshot(snapshot).unwrap_or_else(|error| panic!("{}", error));
+        let options = parse_options(&args[3..]);
+        if options.diagnostic.is_some() {
+            panic!("A diagnostic run starts from the beginning of a program");
+        }
+        (machine, options)
+    } else {
+        let filename = &args[1];
+        println!("input file is: {}", filename);
+        let program = intcode::load_program(filename).unwrap_or_else(|error| panic!("{}", error));
+        println!("{:?}", program);
+        if args.len() == 2 {
+            get_mutated_sequence(&mut program.clone()).unwrap_or_else(|error| panic!("{}", error));
+            return;
+        }
+        (Machine::new(program), parse_options(&args[2..]))
+    };
 
-    get_mutated_sequence(&mut input_sequence.clone());
+    let machine = machine.with_limits(options.limits);
+    if options.ascii {
+        run_ascii(machine);
+        return;
+    }
+    if let Some(system_id) = options.diagnostic {
+        let report = intcode::run_diagnostic(machine.memory(), system_id, options.limits)
+            .unwrap_or_else(|error| panic!("{}", error));
+        println!("{}", report);
+        if !report.passed() {
+            process::exit(1);
+        }
+        return;
+    }
+    let outputs = match run_machine(machine, &options) {
+        Some(outputs) => outputs,
+        None => return,
+    };
+    if let Some(expected) = &options.expected {
+        let differences = diff_outputs(expected, &outputs);
+        if !differences.is_empty() {
+            println!("Outputs differ from expected:");
+            for difference in differences {
+                println!("  {}", difference);
+            }
+            process::exit(1);
+        }
+        println!("All {} outputs as expected", outputs.len());
+    }
 }
 
 #[test]
@@ -311,6 +507,20 @@ fn test_input_sequence4() {
     assert_eq!(input, [30, 1, 1, 4, 2, 5, 6, 0, 99]);
 }
 
+#[test]
+fn test_unknown_op_code() {
+    for program in [vec![1101, 1, 1, 5, 42, 0], vec![1, 0, 0, 0]] {
+        assert_eq!(
+            Machine::new(program.clone()).run().map(|_| ()),
+            get_mutated_sequence(&mut program.clone()).map(|_| ())
+        );
+    }
+    assert_eq!(
+        Err(MachineError::UnknownOpcode { ip: 4, value: 42 }),
+        get_mutated_sequence(&mut [1101, 1, 1, 5, 42, 0]).map(|_| ())
+    );
+}
+
 #[test]
 fn test_op_code_parser() {
     let op = get_operator(1002);
@@ -338,3 +548,27 @@ fn test_new_sequence2() {
     assert_eq!(99, input[4]);
     assert_eq!(input, [1102, 3, 33, 4, 99]);
 }
+
+#[test]
+fn test_diff_outputs() {
+    assert!(diff_outputs(&[0, 0, 7], &[0, 0, 7]).is_empty());
+    assert_eq!(
+        vec![
+            "output 2: expected 0, got 3".to_string(),
+            "output 4: expected 9, got nothing".to_string(),
+        ],
+        diff_outputs(&[0, 0, 7, 9], &[0, 3, 7])
+    );
+    assert_eq!(
+        vec!["output 1: expected nothing, got 5".to_string()],
+        diff_outputs(&[], &[5])
+    );
+}
+
+#[test]
+fn test_scripted_inputs() {
+    let options = parse_options(&["--inputs".to_string(), "8".to_string()]);
+    // Outputs 1 when the input equals 8.
+    let machine = Machine::new(vec![3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8]);
+    assert_eq!(Some(vec![1]), run_machine(machine, &options));
+}
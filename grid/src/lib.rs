@@ -0,0 +1,428 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::{Add, Sub};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct Point {
+    pub x: i64,
+    pub y: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Connectivity {
+    Four,
+    Eight,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    pub min: Point,
+    pub max: Point,
+}
+
+const FOUR_CONNECTED: [Point; 4] = [
+    Point { x: 0, y: -1 },
+    Point { x: 1, y: 0 },
+    Point { x: 0, y: 1 },
+    Point { x: -1, y: 0 },
+];
+
+const EIGHT_CONNECTED: [Point; 8] = [
+    Point { x: 0, y: -1 },
+    Point { x: 1, y: -1 },
+    Point { x: 1, y: 0 },
+    Point { x: 1, y: 1 },
+    Point { x: 0, y: 1 },
+    Point { x: -1, y: 1 },
+    Point { x: -1, y: 0 },
+    Point { x: -1, y: -1 },
+];
+
+impl Point {
+    pub const ORIGIN: Point = Point { x: 0, y: 0 };
+
+    pub fn new(x: i64, y: i64) -> Point {
+        Point { x, y }
+    }
+
+    pub fn manhattan_distance(self, other: Point) -> i64 {
+        (self.x - other.x).abs() + (self.y - other.y).abs()
+    }
+
+    pub fn chebyshev_distance(self, other: Point) -> i64 {
+        (self.x - other.x).abs().max((self.y - other.y).abs())
+    }
+
+    pub fn neighbors(self, connectivity: Connectivity) -> impl Iterator<Item = Point> {
+        let offsets: &'static [Point] = match connectivity {
+            Connectivity::Four => &FOUR_CONNECTED,
+            Connectivity::Eight => &EIGHT_CONNECTED,
+        };
+        offsets.iter().map(move |&offset| self + offset)
+    }
+}
+
+impl Add for Point {
+    type Output = Point;
+
+    fn add(self, other: Point) -> Point {
+        Point::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl Sub for Point {
+    type Output = Point;
+
+    fn sub(self, other: Point) -> Point {
+        Point::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl Bounds {
+    pub fn from_point(point: Point) -> Bounds {
+        Bounds {
+            min: point,
+            max: point,
+        }
+    }
+
+    pub fn include(&mut self, point: Point) {
+        self.min.x = self.min.x.min(point.x);
+        self.min.y = self.min.y.min(point.y);
+        self.max.x = self.max.x.max(point.x);
+        self.max.y = self.max.y.max(point.y);
+    }
+
+    pub fn contains(&self, point: Point) -> bool {
+        (self.min.x..=self.max.x).contains(&point.x) && (self.min.y..=self.max.y).contains(&point.y)
+    }
+
+    pub fn width(&self) -> usize {
+        (self.max.x - self.min.x + 1) as usize
+    }
+
+    pub fn height(&self) -> usize {
+        (self.max.y - self.min.y + 1) as usize
+    }
+}
+
+/// Backing store for a `Grid`, mapping points to cell values.
+pub trait Storage<T>: Default {
+    fn get(&self, point: Point) -> Option<&T>;
+    fn get_mut(&mut self, point: Point) -> Option<&mut T>;
+    fn insert(&mut self, point: Point, value: T) -> Option<T>;
+    fn len(&self) -> usize;
+    fn iter(&self) -> Box<dyn Iterator<Item = (Point, &T)> + '_>;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Hash map backed storage, suited for long thin shapes such as wires.
+#[derive(Debug, Clone)]
+pub struct Sparse<T> {
+    cells: HashMap<Point, T>,
+}
+
+impl<T> Default for Sparse<T> {
+    fn default() -> Self {
+        Sparse {
+            cells: HashMap::new(),
+        }
+    }
+}
+
+impl<T> Storage<T> for Sparse<T> {
+    fn get(&self, point: Point) -> Option<&T> {
+        self.cells.get(&point)
+    }
+
+    fn get_mut(&mut self, point: Point) -> Option<&mut T> {
+        self.cells.get_mut(&point)
+    }
+
+    fn insert(&mut self, point: Point, value: T) -> Option<T> {
+        self.cells.insert(point, value)
+    }
+
+    fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (Point, &T)> + '_> {
+        Box::new(self.cells.iter().map(|(&point, value)| (point, value)))
+    }
+}
+
+/// Row-major vector backed storage, suited for filled rectangular maps.
+/// The allocated area grows to cover any point inserted outside of it.
+#[derive(Debug, Clone)]
+pub struct Dense<T> {
+    area: Option<Bounds>,
+    cells: Vec<Option<T>>,
+    len: usize,
+}
+
+impl<T> Default for Dense<T> {
+    fn default() -> Self {
+        Dense {
+            area: None,
+            cells: vec![],
+            len: 0,
+        }
+    }
+}
+
+impl<T> Dense<T> {
+    fn index_of(&self, point: Point) -> Option<usize> {
+        let area = self.area?;
+        if !area.contains(point) {
+            return None;
+        }
+        let column = (point.x - area.min.x) as usize;
+        let row = (point.y - area.min.y) as usize;
+        Some(row * area.width() + column)
+    }
+
+    fn grow_to(&mut self, point: Point) {
+        let new_area = match self.area {
+            None => Bounds::from_point(point),
+            Some(area) => {
+                // Leave some slack in the growth direction so that walking
+                // off the edge one cell at a time does not reallocate every step.
+                let slack_x = area.width() as i64;
+                let slack_y = area.height() as i64;
+                let mut new_area = area;
+                if point.x < area.min.x {
+                    new_area.min.x = point.x - slack_x;
+                }
+                if point.x > area.max.x {
+                    new_area.max.x = point.x + slack_x;
+                }
+                if point.y < area.min.y {
+                    new_area.min.y = point.y - slack_y;
+                }
+                if point.y > area.max.y {
+                    new_area.max.y = point.y + slack_y;
+                }
+                new_area
+            }
+        };
+
+        let mut cells: Vec<Option<T>> = Vec::with_capacity(new_area.width() * new_area.height());
+        cells.resize_with(new_area.width() * new_area.height(), || None);
+        if let Some(area) = self.area {
+            for (n, cell) in self.cells.drain(..).enumerate() {
+                let old = Point::new(
+                    area.min.x + (n % area.width()) as i64,
+                    area.min.y + (n / area.width()) as i64,
+                );
+                let column = (old.x - new_area.min.x) as usize;
+                let row = (old.y - new_area.min.y) as usize;
+                cells[row * new_area.width() + column] = cell;
+            }
+        }
+        self.area = Some(new_area);
+        self.cells = cells;
+    }
+}
+
+impl<T> Storage<T> for Dense<T> {
+    fn get(&self, point: Point) -> Option<&T> {
+        let index = self.index_of(point)?;
+        self.cells[index].as_ref()
+    }
+
+    fn get_mut(&mut self, point: Point) -> Option<&mut T> {
+        let index = self.index_of(point)?;
+        self.cells[index].as_mut()
+    }
+
+    fn insert(&mut self, point: Point, value: T) -> Option<T> {
+        if self.index_of(point).is_none() {
+            self.grow_to(point);
+        }
+        let index = self.index_of(point).unwrap();
+        let previous = self.cells[index].replace(value);
+        if previous.is_none() {
+            self.len += 1;
+        }
+        previous
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (Point, &T)> + '_> {
+        let area = match self.area {
+            Some(area) => area,
+            None => return Box::new(std::iter::empty()),
+        };
+        Box::new(self.cells.iter().enumerate().filter_map(move |(n, cell)| {
+            let point = Point::new(
+                area.min.x + (n % area.width()) as i64,
+                area.min.y + (n / area.width()) as i64,
+            );
+            cell.as_ref().map(|value| (point, value))
+        }))
+    }
+}
+
+/// A 2D grid over signed coordinates which keeps track of the bounding box
+/// of every cell that has been set.
+#[derive(Debug, Clone)]
+pub struct Grid<T, S: Storage<T> = Sparse<T>> {
+    storage: S,
+    bounds: Option<Bounds>,
+    marker: std::marker::PhantomData<T>,
+}
+
+pub type SparseGrid<T> = Grid<T, Sparse<T>>;
+pub type DenseGrid<T> = Grid<T, Dense<T>>;
+
+impl<T, S: Storage<T>> Default for Grid<T, S> {
+    fn default() -> Self {
+        Grid {
+            storage: S::default(),
+            bounds: None,
+            marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<T, S: Storage<T>> Grid<T, S> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, point: Point) -> Option<&T> {
+        self.storage.get(point)
+    }
+
+    pub fn get_mut(&mut self, point: Point) -> Option<&mut T> {
+        self.storage.get_mut(point)
+    }
+
+    pub fn contains(&self, point: Point) -> bool {
+        self.storage.get(point).is_some()
+    }
+
+    pub fn insert(&mut self, point: Point, value: T) -> Option<T> {
+        match self.bounds.as_mut() {
+            Some(bounds) => bounds.include(point),
+            None => self.bounds = Some(Bounds::from_point(point)),
+        }
+        self.storage.insert(point, value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.storage.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.storage.is_empty()
+    }
+
+    /// Bounding box of all cells set so far, `None` for an empty grid.
+    pub fn bounds(&self) -> Option<Bounds> {
+        self.bounds
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Point, &T)> + '_ {
+        self.storage.iter()
+    }
+
+    /// Occupied cells adjacent to `point`.
+    pub fn neighbors(
+        &self,
+        point: Point,
+        connectivity: Connectivity,
+    ) -> impl Iterator<Item = (Point, &T)> + '_ {
+        point
+            .neighbors(connectivity)
+            .filter_map(move |neighbor| self.get(neighbor).map(|value| (neighbor, value)))
+    }
+}
+
+/// Renders the grid within its bounds, one row per line, with `.` for empty cells.
+impl<T: fmt::Display, S: Storage<T>> fmt::Display for Grid<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let bounds = match self.bounds {
+            Some(bounds) => bounds,
+            None => return Ok(()),
+        };
+        for y in bounds.min.y..=bounds.max.y {
+            for x in bounds.min.x..=bounds.max.x {
+                match self.get(Point::new(x, y)) {
+                    Some(value) => write!(f, "{}", value)?,
+                    None => write!(f, ".")?,
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_neighbors() {
+    let point = Point::new(2, -3);
+    let four: Vec<Point> = point.neighbors(Connectivity::Four).collect();
+    assert_eq!(
+        four,
+        [
+            Point::new(2, -4),
+            Point::new(3, -3),
+            Point::new(2, -2),
+            Point::new(1, -3)
+        ]
+    );
+    assert_eq!(8, point.neighbors(Connectivity::Eight).count());
+}
+
+#[test]
+fn test_bounds_tracking() {
+    let mut grid: SparseGrid<char> = Grid::new();
+    assert_eq!(None, grid.bounds());
+    grid.insert(Point::new(-2, 5), 'a');
+    grid.insert(Point::new(3, -1), 'b');
+    grid.insert(Point::new(3, -1), 'c');
+
+    let bounds = grid.bounds().unwrap();
+    assert_eq!(Point::new(-2, -1), bounds.min);
+    assert_eq!(Point::new(3, 5), bounds.max);
+    assert_eq!(2, grid.len());
+    assert_eq!(Some(&'c'), grid.get(Point::new(3, -1)));
+}
+
+#[test]
+fn test_dense_matches_sparse() {
+    let mut sparse: SparseGrid<u8> = Grid::new();
+    let mut dense: DenseGrid<u8> = Grid::new();
+    let mut point = Point::ORIGIN;
+    for n in 0..50u8 {
+        point = point + EIGHT_CONNECTED[(n as usize * 3) % 8];
+        sparse.insert(point, n);
+        dense.insert(point, n);
+    }
+
+    assert_eq!(sparse.len(), dense.len());
+    assert_eq!(sparse.bounds(), dense.bounds());
+    for (point, value) in sparse.iter() {
+        assert_eq!(Some(value), dense.get(point));
+    }
+    assert_eq!(sparse.to_string(), dense.to_string());
+}
+
+#[test]
+fn test_display() {
+    let mut grid: DenseGrid<char> = Grid::new();
+    grid.insert(Point::new(0, 0), '#');
+    grid.insert(Point::new(2, 1), '#');
+    let neighbors = grid.neighbors(Point::new(1, 0), Connectivity::Eight);
+
+    assert_eq!(2, neighbors.count());
+    assert_eq!("#..\n..#\n", grid.to_string());
+}
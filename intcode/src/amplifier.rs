@@ -0,0 +1,173 @@
+use std::fmt;
+
+use crate::{Machine, Network, NetworkError, Outcome, Topology};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Wiring {
+    /// The signal passes through every amplifier once.
+    Series,
+    /// The last amplifier feeds the first until they all halt.
+    Feedback,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AmplifierError {
+    Machine(NetworkError),
+    /// Some amplifiers got stuck waiting for input before halting.
+    Deadlocked {
+        waiting: Vec<usize>,
+    },
+    /// The last amplifier never output a signal.
+    NoSignal,
+}
+
+impl fmt::Display for AmplifierError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AmplifierError::Machine(error) => write!(f, "{}", error),
+            AmplifierError::Deadlocked { waiting } => {
+                write!(f, "amplifiers {:?} are waiting for input", waiting)
+            }
+            AmplifierError::NoSignal => write!(f, "the last amplifier sent no signal"),
+        }
+    }
+}
+
+impl From<NetworkError> for AmplifierError {
+    fn from(error: NetworkError) -> Self {
+        AmplifierError::Machine(error)
+    }
+}
+
+/// Runs one copy of `program` per phase setting, each reading its phase
+/// and then the previous amplifier's output, starting from a signal of 0.
+/// Returns the last signal the final amplifier sent.
+pub fn run_amplifiers(
+    program: &[i64],
+    phases: &[i64],
+    wiring: Wiring,
+) -> Result<i64, AmplifierError> {
+    let topology = match wiring {
+        Wiring::Series => Topology::Pipeline,
+        Wiring::Feedback => Topology::Ring,
+    };
+    let machines = vec![Machine::new(program.to_vec()); phases.len()];
+    let mut network = Network::new(machines, topology);
+    if network.is_empty() {
+        return Err(AmplifierError::NoSignal);
+    }
+    for (address, &phase) in phases.iter().enumerate() {
+        network.send(address, phase);
+    }
+    network.send(0, 0);
+
+    match network.run()? {
+        Outcome::Deadlocked { waiting } => return Err(AmplifierError::Deadlocked { waiting }),
+        Outcome::Halted | Outcome::Idle => {}
+    }
+    network
+        .last_output(phases.len() - 1)
+        .ok_or(AmplifierError::NoSignal)
+}
+
+/// Every ordering of `values`, generated with Heap's algorithm.
+pub fn permutations(values: &[i64]) -> Vec<Vec<i64>> {
+    let mut current = values.to_vec();
+    let mut counters = vec![0; current.len()];
+    let mut result = vec![current.clone()];
+    let mut i = 1;
+    while i < current.len() {
+        if counters[i] < i {
+            let swap = if i % 2 == 0 { 0 } else { counters[i] };
+            current.swap(swap, i);
+            result.push(current.clone());
+            counters[i] += 1;
+            i = 1;
+        } else {
+            counters[i] = 0;
+            i += 1;
+        }
+    }
+    result
+}
+
+/// Tries every ordering of `phases` and returns the one giving the highest
+/// final signal, together with that signal.
+pub fn best_phase_settings(
+    program: &[i64],
+    phases: &[i64],
+    wiring: Wiring,
+) -> Result<(Vec<i64>, i64), AmplifierError> {
+    let mut best: Option<(Vec<i64>, i64)> = None;
+    for settings in permutations(phases) {
+        let signal = run_amplifiers(program, &settings, wiring)?;
+        if best.as_ref().is_none_or(|&(_, highest)| signal > highest) {
+            best = Some((settings, signal));
+        }
+    }
+    best.ok_or(AmplifierError::NoSignal)
+}
+
+#[test]
+fn test_permutations() {
+    let mut all = permutations(&[0, 1, 2]);
+    all.sort();
+    assert_eq!(
+        vec![
+            vec![0, 1, 2],
+            vec![0, 2, 1],
+            vec![1, 0, 2],
+            vec![1, 2, 0],
+            vec![2, 0, 1],
+            vec![2, 1, 0],
+        ],
+        all
+    );
+    assert_eq!(120, permutations(&[5, 6, 7, 8, 9]).len());
+    assert_eq!(vec![Vec::<i64>::new()], permutations(&[]));
+}
+
+#[test]
+fn test_series_amplifiers() {
+    let program = vec![
+        3, 15, 3, 16, 1002, 16, 10, 16, 1, 16, 15, 15, 4, 15, 99, 0, 0,
+    ];
+    assert_eq!(
+        Ok(43210),
+        run_amplifiers(&program, &[4, 3, 2, 1, 0], Wiring::Series)
+    );
+    assert_eq!(
+        Ok((vec![4, 3, 2, 1, 0], 43210)),
+        best_phase_settings(&program, &[0, 1, 2, 3, 4], Wiring::Series)
+    );
+}
+
+#[test]
+fn test_feedback_amplifiers() {
+    let program = vec![
+        3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1, 28,
+        1005, 28, 6, 99, 0, 0, 5,
+    ];
+    assert_eq!(
+        Ok(139_629_729),
+        run_amplifiers(&program, &[9, 8, 7, 6, 5], Wiring::Feedback)
+    );
+    assert_eq!(
+        Ok((vec![9, 8, 7, 6, 5], 139_629_729)),
+        best_phase_settings(&program, &[5, 6, 7, 8, 9], Wiring::Feedback)
+    );
+}
+
+#[test]
+fn test_amplifier_errors() {
+    // Reads its phase and the signal but never sends anything on.
+    let program = vec![3, 0, 3, 0, 99];
+    assert_eq!(
+        Err(AmplifierError::Deadlocked { waiting: vec![1] }),
+        run_amplifiers(&program, &[0, 1], Wiring::Series)
+    );
+    assert_eq!(
+        Err(AmplifierError::NoSignal),
+        run_amplifiers(&[99], &[0], Wiring::Series)
+    );
+}
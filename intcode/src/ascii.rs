@@ -0,0 +1,103 @@
+use std::fmt;
+
+use crate::{Machine, MachineError, Status};
+
+/// Decoded output: runs of ASCII text, and values outside the ASCII range
+/// passed through as numbers.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Chunk {
+    Text(String),
+    Value(i64),
+}
+
+impl fmt::Display for Chunk {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Chunk::Text(text) => write!(f, "{}", text),
+            Chunk::Value(value) => writeln!(f, "[{}]", value),
+        }
+    }
+}
+
+/// Character codes for a line of text, ending with a newline.
+pub fn encode_line(line: &str) -> Vec<i64> {
+    line.chars()
+        .chain(std::iter::once('\n'))
+        .map(|c| c as i64)
+        .collect()
+}
+
+pub fn decode(values: &[i64]) -> Vec<Chunk> {
+    let mut chunks = vec![];
+    for &value in values {
+        match (value, chunks.last_mut()) {
+            (0..=127, Some(Chunk::Text(text))) => text.push(value as u8 as char),
+            (0..=127, _) => chunks.push(Chunk::Text((value as u8 as char).to_string())),
+            _ => chunks.push(Chunk::Value(value)),
+        }
+    }
+    chunks
+}
+
+/// Runs a program that talks in lines of text instead of single integers.
+pub struct AsciiMachine {
+    machine: Machine,
+}
+
+impl AsciiMachine {
+    pub fn new(machine: Machine) -> AsciiMachine {
+        AsciiMachine { machine }
+    }
+
+    pub fn machine(&self) -> &Machine {
+        &self.machine
+    }
+
+    pub fn into_inner(self) -> Machine {
+        self.machine
+    }
+
+    pub fn send_line(&mut self, line: &str) {
+        for code in encode_line(line) {
+            self.machine.push_input(code);
+        }
+    }
+
+    /// Runs until the program halts or wants another line, returning what it
+    /// printed in the meantime.
+    pub fn run(&mut self) -> Result<(Status, Vec<Chunk>), MachineError> {
+        let status = self.machine.run()?;
+        Ok((status, decode(&self.machine.take_output())))
+    }
+}
+
+#[test]
+fn test_encode_and_decode() {
+    assert_eq!(vec![78, 79, 84, 32, 65, 10], encode_line("NOT A"));
+    assert_eq!(
+        vec![
+            Chunk::Text("Hi\n".to_string()),
+            Chunk::Value(19_349_530),
+            Chunk::Text("!".to_string()),
+            Chunk::Value(-1),
+        ],
+        decode(&[72, 105, 10, 19_349_530, 33, -1])
+    );
+    assert_eq!("[128]\n", Chunk::Value(128).to_string());
+}
+
+#[test]
+fn test_echo_program() {
+    // Echoes characters back, upper-casing lower case letters, until it reads `.`.
+    let program = vec![
+        3, 100, 1008, 100, 46, 101, 1005, 101, 38, 1007, 100, 97, 102, 1007, 100, 123, 103, 1008,
+        102, 0, 102, 2, 102, 103, 104, 1002, 104, -32, 104, 1, 100, 104, 105, 4, 105, 1105, 1, 0,
+        99,
+    ];
+    let mut machine = AsciiMachine::new(Machine::new(program));
+    assert_eq!(Ok((Status::NeedsInput, vec![])), machine.run());
+    machine.send_line("hello, Intcode.");
+    let (status, chunks) = machine.run().unwrap();
+    assert_eq!(Status::Halted, status);
+    assert_eq!(vec![Chunk::Text("HELLO, INTCODE".to_string())], chunks);
+}
@@ -0,0 +1,326 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use crate::{Exit, Machine, MachineError, Status};
+
+/// Something an async machine can await input values from. `None` means the
+/// stream has ended and no more input will come.
+pub trait InputStream {
+    fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<i64>>;
+}
+
+/// The sink has been closed and the value was dropped.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SinkClosed;
+
+impl fmt::Display for SinkClosed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "output sink is closed")
+    }
+}
+
+/// Something an async machine can push its outputs into.
+pub trait OutputSink {
+    fn poll_send(&mut self, cx: &mut Context<'_>, value: i64) -> Poll<Result<(), SinkClosed>>;
+}
+
+/// Plain iterators are input streams that never wait.
+impl<T: Iterator<Item = i64>> InputStream for T {
+    fn poll_next(&mut self, _cx: &mut Context<'_>) -> Poll<Option<i64>> {
+        Poll::Ready(self.next())
+    }
+}
+
+impl OutputSink for Vec<i64> {
+    fn poll_send(&mut self, _cx: &mut Context<'_>, value: i64) -> Poll<Result<(), SinkClosed>> {
+        self.push(value);
+        Poll::Ready(Ok(()))
+    }
+}
+
+struct Next<'a, S: ?Sized>(&'a mut S);
+
+impl<S: InputStream + ?Sized> Future for Next<'_, S> {
+    type Output = Option<i64>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.get_mut().0.poll_next(cx)
+    }
+}
+
+struct Send<'a, S: ?Sized> {
+    sink: &'a mut S,
+    value: i64,
+}
+
+impl<S: OutputSink + ?Sized> Future for Send<'_, S> {
+    type Output = Result<(), SinkClosed>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        this.sink.poll_send(cx, this.value)
+    }
+}
+
+/// A machine whose `Input` instruction awaits `input` and whose `Output`
+/// instruction awaits `output`.
+pub struct AsyncMachine<I, O> {
+    machine: Machine,
+    input: I,
+    output: O,
+}
+
+impl<I: InputStream, O: OutputSink> AsyncMachine<I, O> {
+    pub fn new(machine: Machine, input: I, output: O) -> AsyncMachine<I, O> {
+        AsyncMachine {
+            machine,
+            input,
+            output,
+        }
+    }
+
+    pub fn machine(&self) -> &Machine {
+        &self.machine
+    }
+
+    pub fn into_parts(self) -> (Machine, I, O) {
+        (self.machine, self.input, self.output)
+    }
+
+    /// Runs until the machine halts or either end has been closed.
+    pub async fn run(&mut self) -> Result<Exit, MachineError> {
+        loop {
+            let status = self.machine.step()?;
+            while let Some(value) = self.machine.pop_output() {
+                let send = Send {
+                    sink: &mut self.output,
+                    value,
+                };
+                if send.await.is_err() {
+                    return Ok(Exit::OutputClosed);
+                }
+            }
+            match status {
+                Status::Running => {}
+                Status::Halted => return Ok(Exit::Halted),
+                Status::NeedsInput => match Next(&mut self.input).await {
+                    Some(value) => self.machine.push_input(value),
+                    None => return Ok(Exit::InputClosed),
+                },
+            }
+        }
+    }
+}
+
+struct Shared {
+    queue: VecDeque<i64>,
+    waker: Option<Waker>,
+    senders: usize,
+    receiver_alive: bool,
+}
+
+/// Sending half of an unbounded async channel, usable as an `OutputSink`.
+pub struct Sender {
+    shared: Arc<Mutex<Shared>>,
+}
+
+/// Receiving half of an unbounded async channel, usable as an `InputStream`.
+/// It ends once every sender has been dropped and the queue is empty.
+pub struct Receiver {
+    shared: Arc<Mutex<Shared>>,
+}
+
+pub fn channel() -> (Sender, Receiver) {
+    let shared = Arc::new(Mutex::new(Shared {
+        queue: VecDeque::new(),
+        waker: None,
+        senders: 1,
+        receiver_alive: true,
+    }));
+    (
+        Sender {
+            shared: Arc::clone(&shared),
+        },
+        Receiver { shared },
+    )
+}
+
+impl Sender {
+    /// Queues a value without waiting. Returns `false` if the receiver is gone.
+    pub fn send(&self, value: i64) -> bool {
+        let mut shared = self.shared.lock().unwrap();
+        if !shared.receiver_alive {
+            return false;
+        }
+        shared.queue.push_back(value);
+        if let Some(waker) = shared.waker.take() {
+            waker.wake();
+        }
+        true
+    }
+}
+
+impl Clone for Sender {
+    fn clone(&self) -> Self {
+        self.shared.lock().unwrap().senders += 1;
+        Sender {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl Drop for Sender {
+    fn drop(&mut self) {
+        let mut shared = self.shared.lock().unwrap();
+        shared.senders -= 1;
+        if shared.senders == 0 {
+            if let Some(waker) = shared.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+impl Drop for Receiver {
+    fn drop(&mut self) {
+        self.shared.lock().unwrap().receiver_alive = false;
+    }
+}
+
+impl OutputSink for Sender {
+    fn poll_send(&mut self, _cx: &mut Context<'_>, value: i64) -> Poll<Result<(), SinkClosed>> {
+        Poll::Ready(if self.send(value) {
+            Ok(())
+        } else {
+            Err(SinkClosed)
+        })
+    }
+}
+
+impl InputStream for Receiver {
+    fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<i64>> {
+        let mut shared = self.shared.lock().unwrap();
+        if let Some(value) = shared.queue.pop_front() {
+            return Poll::Ready(Some(value));
+        }
+        if shared.senders == 0 {
+            return Poll::Ready(None);
+        }
+        shared.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+/// Minimal single-threaded executor for the tests: polls every future in
+/// turn, failing if all of them wait with nothing left to wake them.
+#[cfg(test)]
+fn run_all<T>(mut futures: Vec<Pin<Box<dyn Future<Output = T> + '_>>>) -> Vec<T> {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::task::Wake;
+
+    struct Flag(AtomicBool);
+
+    impl Wake for Flag {
+        fn wake(self: Arc<Self>) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    let flag = Arc::new(Flag(AtomicBool::new(true)));
+    let waker = Waker::from(Arc::clone(&flag));
+    let mut cx = Context::from_waker(&waker);
+    let mut results: Vec<Option<T>> = futures.iter().map(|_| None).collect();
+    while results.iter().any(Option::is_none) {
+        assert!(
+            flag.0.swap(false, Ordering::SeqCst),
+            "every future is waiting and nothing will wake them"
+        );
+        for (future, result) in futures.iter_mut().zip(results.iter_mut()) {
+            if result.is_none() {
+                if let Poll::Ready(value) = future.as_mut().poll(&mut cx) {
+                    *result = Some(value);
+                    flag.0.store(true, Ordering::SeqCst);
+                }
+            }
+        }
+    }
+    results.into_iter().map(Option::unwrap).collect()
+}
+
+#[test]
+fn test_async_machine_with_iterator_and_vec() {
+    // Outputs 1 when the input equals 8, 0 otherwise.
+    let program = vec![3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
+    let mut machine = AsyncMachine::new(Machine::new(program), vec![8].into_iter(), vec![]);
+    let exits = run_all(vec![Box::pin(machine.run())]);
+    assert_eq!(vec![Ok(Exit::Halted)], exits);
+    let (_, _, output) = machine.into_parts();
+    assert_eq!(vec![1], output);
+}
+
+#[test]
+fn test_async_feedback_loop() {
+    let program = vec![
+        3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1, 28,
+        1005, 28, 6, 99, 0, 0, 5,
+    ];
+    let phases = [9, 8, 7, 6, 5];
+    let (senders, receivers): (Vec<Sender>, Vec<Receiver>) =
+        phases.iter().map(|_| channel()).unzip();
+    for (sender, &phase) in senders.iter().zip(phases.iter()) {
+        sender.send(phase);
+    }
+    senders[0].send(0);
+
+    // The last amplifier also copies its signals into a tap for the test.
+    let (tap, mut signals) = channel();
+    let mut outputs: Vec<Sender> = senders.iter().skip(1).cloned().collect();
+    outputs.push(tap);
+    let first = senders[0].clone();
+    drop(senders);
+
+    let machines = receivers
+        .into_iter()
+        .zip(outputs)
+        .map(|(input, output)| AsyncMachine::new(Machine::new(program.clone()), input, output));
+    let mut futures: Vec<Pin<Box<dyn Future<Output = Option<i64>>>>> = machines
+        .map(|mut machine| -> Pin<Box<dyn Future<Output = _>>> {
+            Box::pin(async move {
+                assert_eq!(Ok(Exit::Halted), machine.run().await);
+                None
+            })
+        })
+        .collect();
+    futures.push(Box::pin(async move {
+        let mut last = None;
+        while let Some(value) = Next(&mut signals).await {
+            last = Some(value);
+            first.send(value);
+        }
+        last
+    }));
+
+    let results = run_all(futures);
+    assert_eq!(Some(&Some(139_629_729)), results.last());
+}
+
+#[test]
+fn test_closed_sink() {
+    let (sender, receiver) = channel();
+    drop(receiver);
+    let mut machine = AsyncMachine::new(Machine::new(vec![104, 7, 99]), std::iter::empty(), sender);
+    assert_eq!(
+        vec![Ok(Exit::OutputClosed)],
+        run_all(vec![Box::pin(machine.run())])
+    );
+    let (_, _, mut sender) = machine.into_parts();
+    let waker = Waker::noop();
+    assert_eq!(
+        Poll::Ready(Err(SinkClosed)),
+        sender.poll_send(&mut Context::from_waker(waker), 7)
+    );
+}
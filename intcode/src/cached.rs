@@ -0,0 +1,86 @@
+use crate::{decode, Decoded, Machine, MachineError, Status};
+
+/// A `Machine` that remembers decoded instructions by address. Each entry
+/// keeps the raw value it was decoded from, so self-modifying programs just
+/// decode again instead of needing invalidation.
+#[derive(Debug, Clone)]
+pub struct CachedMachine {
+    machine: Machine,
+    cache: Vec<Option<(i64, Decoded)>>,
+}
+
+impl CachedMachine {
+    pub fn new(machine: Machine) -> CachedMachine {
+        CachedMachine {
+            machine,
+            cache: vec![],
+        }
+    }
+
+    pub fn machine(&self) -> &Machine {
+        &self.machine
+    }
+
+    pub fn machine_mut(&mut self) -> &mut Machine {
+        &mut self.machine
+    }
+
+    pub fn into_inner(self) -> Machine {
+        self.machine
+    }
+
+    fn decoded(&mut self) -> Result<Decoded, MachineError> {
+        let ip = self.machine.ip();
+        let value = self.machine.read(ip);
+        if let Some(Some((cached, decoded))) = self.cache.get(ip) {
+            if *cached == value {
+                return Ok(*decoded);
+            }
+        }
+        let decoded = decode(value, ip)?;
+        // Only addresses inside the program are cached, so a jump far past
+        // the end cannot grow the cache without bound.
+        if ip < self.machine.memory().len() {
+            if ip >= self.cache.len() {
+                self.cache.resize(self.machine.memory().len(), None);
+            }
+            self.cache[ip] = Some((value, decoded));
+        }
+        Ok(decoded)
+    }
+
+    /// Same as `Machine::step`, reusing earlier decodes.
+    pub fn step(&mut self) -> Result<Status, MachineError> {
+        self.machine.check_step_limit()?;
+        let decoded = self.decoded()?;
+        self.machine.execute(decoded)
+    }
+
+    pub fn run(&mut self) -> Result<Status, MachineError> {
+        loop {
+            match self.step()? {
+                Status::Running => {}
+                status => return Ok(status),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_self_modifying_program() {
+    // Runs `jf 0, 3` at 10, then turns it into `jt 0, 3` and runs it again.
+    // A stale cache would jump back to 3 forever.
+    use crate::Limits;
+
+    let program = vec![1105, 1, 10, 101, -1, 10, 10, 1105, 1, 10, 1106, 0, 3, 99];
+    let limits = Limits {
+        max_steps: Some(20),
+        ..Limits::default()
+    };
+    let mut machine = CachedMachine::new(Machine::new(program.clone()).with_limits(limits));
+    assert_eq!(Ok(Status::Halted), machine.run());
+    let mut reference = Machine::new(program).with_limits(limits);
+    assert_eq!(Ok(Status::Halted), reference.run());
+    assert_eq!(5, machine.machine().steps());
+    assert_eq!(&reference, machine.machine());
+}
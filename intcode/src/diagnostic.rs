@@ -0,0 +1,154 @@
+use std::fmt;
+
+use crate::{Limits, Machine, MachineError, Status};
+
+/// A test the diagnostic program reported as failing, by outputting a
+/// non-zero value instead of 0.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestFailure {
+    /// Position of the test among all outputs, counting from 1.
+    pub test: usize,
+    pub value: i64,
+    /// Address of the output instruction that reported it.
+    pub ip: usize,
+}
+
+/// Outputs of a diagnostic run: every output is a test result except the
+/// last one before the program halts, which is the diagnostic code.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiagnosticReport {
+    pub tests: usize,
+    pub failures: Vec<TestFailure>,
+    pub code: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiagnosticError {
+    Machine(MachineError),
+    /// The program asked for more than the system ID.
+    NeedsInput {
+        ip: usize,
+    },
+}
+
+impl fmt::Display for DiagnosticError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DiagnosticError::Machine(error) => write!(f, "{}", error),
+            DiagnosticError::NeedsInput { ip } => {
+                write!(f, "program asked for more input at {}", ip)
+            }
+        }
+    }
+}
+
+impl DiagnosticReport {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty() && self.code.is_some()
+    }
+}
+
+impl fmt::Display for DiagnosticReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.failures.is_empty() {
+            write!(f, "All {} tests passed", self.tests)?;
+        } else {
+            write!(f, "{} of {} tests failed", self.failures.len(), self.tests)?;
+            for failure in &self.failures {
+                write!(
+                    f,
+                    "\n  test {}: output {} at {}",
+                    failure.test, failure.value, failure.ip
+                )?;
+            }
+        }
+        match self.code {
+            Some(code) => write!(f, "\nDiagnostic code: {}", code),
+            None => write!(f, "\nNo diagnostic code"),
+        }
+    }
+}
+
+/// Runs a diagnostic program within `limits`, with the given system ID as
+/// its only input.
+pub fn run_diagnostic(
+    program: &[i64],
+    system_id: i64,
+    limits: Limits,
+) -> Result<DiagnosticReport, DiagnosticError> {
+    let mut machine = Machine::new(program.to_vec()).with_limits(limits);
+    machine.push_input(system_id);
+    let mut outputs: Vec<(i64, usize)> = vec![];
+    loop {
+        let ip = machine.ip();
+        match machine.step().map_err(DiagnosticError::Machine)? {
+            Status::Running => {}
+            Status::NeedsInput => return Err(DiagnosticError::NeedsInput { ip }),
+            Status::Halted => break,
+        }
+        if let Some(value) = machine.pop_output() {
+            outputs.push((value, ip));
+        }
+    }
+
+    let code = outputs.pop().map(|(value, _)| value);
+    let failures = outputs
+        .iter()
+        .enumerate()
+        .filter(|(_, &(value, _))| value != 0)
+        .map(|(n, &(value, ip))| TestFailure {
+            test: n + 1,
+            value,
+            ip,
+        })
+        .collect();
+    Ok(DiagnosticReport {
+        tests: outputs.len(),
+        failures,
+        code,
+    })
+}
+
+#[test]
+fn test_passing_diagnostic() {
+    // Two passing tests, then the system ID times 100 as the code.
+    let program = vec![3, 13, 104, 0, 104, 0, 1002, 13, 100, 13, 4, 13, 99, 0];
+    let report = run_diagnostic(&program, 5, Limits::default()).unwrap();
+    assert_eq!(
+        DiagnosticReport {
+            tests: 2,
+            failures: vec![],
+            code: Some(500),
+        },
+        report
+    );
+    assert!(report.passed());
+    assert_eq!(
+        "All 2 tests passed\nDiagnostic code: 500",
+        report.to_string()
+    );
+}
+
+#[test]
+fn test_failing_diagnostic() {
+    let program = vec![3, 11, 104, 0, 104, 3, 104, 0, 104, 42, 99, 0];
+    let report = run_diagnostic(&program, 1, Limits::default()).unwrap();
+    assert_eq!(
+        vec![TestFailure {
+            test: 2,
+            value: 3,
+            ip: 4,
+        }],
+        report.failures
+    );
+    assert_eq!(Some(42), report.code);
+    assert!(!report.passed());
+    assert_eq!(
+        "1 of 3 tests failed\n  test 2: output 3 at 4\nDiagnostic code: 42",
+        report.to_string()
+    );
+    assert_eq!(
+        Err(DiagnosticError::NeedsInput { ip: 2 }),
+        run_diagnostic(&[3, 0, 3, 0, 99], 1, Limits::default())
+    );
+}
@@ -0,0 +1,194 @@
+use crate::{decode, Decoded, Mode, Opcode};
+
+impl Opcode {
+    pub fn mnemonic(self) -> &'static str {
+        match self {
+            Opcode::Add => "add",
+            Opcode::Multiply => "mul",
+            Opcode::Input => "in",
+            Opcode::Output => "out",
+            Opcode::JumpIfTrue => "jt",
+            Opcode::JumpIfFalse => "jf",
+            Opcode::LessThan => "lt",
+            Opcode::Equals => "eq",
+            Opcode::AdjustRelativeBase => "arb",
+            Opcode::Halt => "hlt",
+        }
+    }
+
+    fn from_mnemonic(x: &str) -> Option<Opcode> {
+        (1..=9)
+            .chain(std::iter::once(99))
+            .filter_map(Opcode::from_i64)
+            .find(|opcode| opcode.mnemonic() == x)
+    }
+
+    fn code(self) -> i64 {
+        (1..=99)
+            .find(|&code| Opcode::from_i64(code) == Some(self))
+            .unwrap()
+    }
+}
+
+impl Mode {
+    fn code(self) -> i64 {
+        match self {
+            Mode::Position => 0,
+            Mode::Immediate => 1,
+            Mode::Relative => 2,
+        }
+    }
+}
+
+/// The instruction at `ip`, if the value there is one that `assemble` would
+/// produce again: a known opcode whose unused parameter modes are zero and
+/// whose parameters all fit in the program.
+fn instruction_at(program: &[i64], ip: usize) -> Option<Decoded> {
+    let decoded = decode(program[ip], ip).ok()?;
+    let count = decoded.opcode.parameter_count();
+    let unused_modes = decoded.modes[count..]
+        .iter()
+        .all(|&mode| mode == Mode::Position);
+    if unused_modes && ip + count < program.len() {
+        Some(decoded)
+    } else {
+        None
+    }
+}
+
+fn format_parameter(mode: Mode, value: i64) -> String {
+    match mode {
+        Mode::Position => format!("[{}]", value),
+        Mode::Immediate => value.to_string(),
+        Mode::Relative if value < 0 => format!("[rb{}]", value),
+        Mode::Relative => format!("[rb+{}]", value),
+    }
+}
+
+/// One line per instruction, prefixed with its address. Values that do not
+/// decode as an instruction are written as `data`.
+pub fn disassemble(program: &[i64]) -> String {
+    let mut output = String::new();
+    let mut ip = 0;
+    while ip < program.len() {
+        let (line, len) = match instruction_at(program, ip) {
+            Some(decoded) => {
+                let count = decoded.opcode.parameter_count();
+                let parameters: Vec<String> = (0..count)
+                    .map(|n| format_parameter(decoded.modes[n], program[ip + 1 + n]))
+                    .collect();
+                let line = format!("{} {}", decoded.opcode.mnemonic(), parameters.join(", "));
+                (line.trim_end().to_string(), 1 + count)
+            }
+            None => (format!("data {}", program[ip]), 1),
+        };
+        output += &format!("{}: {}\n", ip, line);
+        ip += len;
+    }
+    output
+}
+
+fn parse_parameter(text: &str) -> Option<(Mode, i64)> {
+    if let Some(inner) = text.strip_prefix('[').and_then(|x| x.strip_suffix(']')) {
+        return match inner.strip_prefix("rb") {
+            Some(offset) => Some((Mode::Relative, offset.trim_start_matches('+').parse().ok()?)),
+            None => Some((Mode::Position, inner.parse().ok()?)),
+        };
+    }
+    Some((Mode::Immediate, text.parse().ok()?))
+}
+
+/// Assembles the output of `disassemble`. Address prefixes are optional but
+/// must match when present, and `;` starts a comment.
+pub fn assemble(source: &str) -> Result<Vec<i64>, String> {
+    let mut program: Vec<i64> = vec![];
+    for (n, line) in source.lines().enumerate() {
+        let error = |message: &str| format!("line {}: {}", n + 1, message);
+        let mut line = match line.find(';') {
+            Some(pos) => &line[..pos],
+            None => line,
+        }
+        .trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(pos) = line.find(':') {
+            let address: usize = line[..pos]
+                .trim()
+                .parse()
+                .map_err(|_| error("invalid address"))?;
+            if address != program.len() {
+                return Err(error(&format!("expected address {}", program.len())));
+            }
+            line = line[pos + 1..].trim();
+        }
+
+        let (mnemonic, rest) = match line.find(' ') {
+            Some(pos) => (&line[..pos], line[pos..].trim()),
+            None => (line, ""),
+        };
+        let parameters: Vec<&str> = if rest.is_empty() {
+            vec![]
+        } else {
+            rest.split(',').map(str::trim).collect()
+        };
+
+        if mnemonic == "data" {
+            match parameters.as_slice() {
+                [value] => program.push(value.parse().map_err(|_| error("invalid value"))?),
+                _ => return Err(error("data takes one value")),
+            }
+            continue;
+        }
+        let opcode = Opcode::from_mnemonic(mnemonic)
+            .ok_or_else(|| error(&format!("unknown instruction `{}`", mnemonic)))?;
+        if parameters.len() != opcode.parameter_count() {
+            return Err(error(&format!(
+                "{} takes {} parameters",
+                mnemonic,
+                opcode.parameter_count()
+            )));
+        }
+        let mut instruction = opcode.code();
+        let mut values = vec![];
+        for (m, parameter) in parameters.iter().enumerate() {
+            let (mode, value) = parse_parameter(parameter)
+                .ok_or_else(|| error(&format!("invalid parameter `{}`", parameter)))?;
+            instruction += mode.code() * 10_i64.pow(m as u32 + 2);
+            values.push(value);
+        }
+        program.push(instruction);
+        program.extend(values);
+    }
+    Ok(program)
+}
+
+#[test]
+fn test_disassemble() {
+    let program = vec![1002, 4, 3, 4, 33, 109, -7, 21101, 1, 2, 3, 1105];
+    assert_eq!(
+        "0: mul [4], 3, [4]\n4: data 33\n5: arb -7\n7: add 1, 2, [rb+3]\n11: data 1105\n",
+        disassemble(&program)
+    );
+    assert_eq!(Ok(program.clone()), assemble(&disassemble(&program)));
+}
+
+#[test]
+fn test_assemble() {
+    assert_eq!(
+        Ok(vec![3, 0, 4, 0, 204, -2, 99]),
+        assemble("in [0] ; read\nout [0]\n\n  out [rb-2]\nhlt")
+    );
+    assert_eq!(
+        Err("line 2: expected address 2".to_string()),
+        assemble("0: in [0]\n3: hlt")
+    );
+    assert_eq!(
+        Err("line 1: add takes 3 parameters".to_string()),
+        assemble("add 1, 2")
+    );
+    assert_eq!(
+        Err("line 1: unknown instruction `nop`".to_string()),
+        assemble("nop")
+    );
+}
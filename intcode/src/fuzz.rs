@@ -0,0 +1,113 @@
+//! Property tests over random programs: the interpreter only ever returns
+//! errors, the cached interpreter agrees with the plain one, and the
+//! disassembler round-trips.
+
+use proptest::collection::vec;
+use proptest::prelude::*;
+
+use crate::{assemble, disassemble, CachedMachine, Limits, Machine, MachineError, Status};
+
+const LIMITS: Limits = Limits {
+    max_steps: Some(1000),
+    max_memory: Some(4096),
+    max_outputs: Some(100),
+};
+
+/// Mostly small values, so addresses and jumps land inside the program,
+/// with the occasional extreme one to hit the overflow paths.
+fn value() -> impl Strategy<Value = i64> {
+    prop_oneof![
+        8 => -8_i64..56,
+        1 => any::<i64>(),
+        1 => prop_oneof![Just(i64::MIN), Just(i64::MAX), Just(-1), Just(0)],
+    ]
+}
+
+/// A known opcode with any parameter modes, also on parameters the opcode
+/// does not have.
+fn instruction() -> impl Strategy<Value = i64> {
+    (prop_oneof![(1_i64..=9), Just(99)], vec(0_i64..3, 3)).prop_map(|(opcode, modes)| {
+        modes
+            .iter()
+            .zip([100, 1000, 10000].iter())
+            .fold(opcode, |value, (mode, place)| value + mode * place)
+    })
+}
+
+fn program() -> impl Strategy<Value = Vec<i64>> {
+    vec(prop_oneof![1 => instruction(), 2 => value()], 1..48)
+}
+
+/// An address no machine can grow its memory to, or a negative one.
+fn extreme_address() -> impl Strategy<Value = i64> {
+    prop_oneof![(1_i64 << 60)..=i64::MAX, i64::MIN..-100]
+}
+
+/// A single write to an extreme address, either directly or relative to a
+/// small base, followed by `hlt`.
+fn extreme_write() -> impl Strategy<Value = Vec<i64>> {
+    (
+        prop_oneof![Just(1_i64), Just(2), Just(3), Just(7), Just(8)],
+        any::<bool>(),
+        0_i64..100,
+        extreme_address(),
+    )
+        .prop_map(|(opcode, relative, base, address)| {
+            let mode = if relative { 2 } else { 0 };
+            let mut program = if relative { vec![109, base] } else { vec![] };
+            if opcode == 3 {
+                program.extend(&[3 + mode * 100, address]);
+            } else {
+                program.extend(&[opcode + 1100 + mode * 10000, 5, 7, address]);
+            }
+            program.push(99);
+            program
+        })
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(2000))]
+
+    #[test]
+    fn test_random_programs_agree(program in program(), inputs in vec(value(), 4)) {
+        let mut plain = Machine::new(program.clone()).with_limits(LIMITS);
+        let mut cached = CachedMachine::new(Machine::new(program).with_limits(LIMITS));
+        // Feed more input whenever both stop for it, up to a few times.
+        for &input in &inputs {
+            let result = plain.run();
+            prop_assert_eq!(&result, &cached.run());
+            prop_assert_eq!(&plain, cached.machine());
+            if result != Ok(Status::NeedsInput) {
+                break;
+            }
+            plain.push_input(input);
+            cached.machine_mut().push_input(input);
+        }
+    }
+
+    #[test]
+    fn test_extreme_writes_fail_without_limits(program in extreme_write()) {
+        let mut plain = Machine::new(program.clone());
+        plain.push_input(1);
+        let result = plain.run();
+        prop_assert!(
+            matches!(
+                result,
+                Err(MachineError::OutOfMemory { .. })
+                    | Err(MachineError::InvalidAddress { .. })
+                    | Err(MachineError::Overflow { .. })
+            ),
+            "{:?}",
+            result
+        );
+        let mut cached = CachedMachine::new(Machine::new(program));
+        cached.machine_mut().push_input(1);
+        prop_assert_eq!(result, cached.run());
+    }
+
+    #[test]
+    fn test_disassemble_round_trip(program in program()) {
+        let source = disassemble(&program);
+        prop_assert_eq!(Ok(program), assemble(&source), "source:\n{}", source);
+    }
+}
@@ -0,0 +1,29 @@
+mod amplifier;
+mod ascii;
+mod asynchronous;
+mod cached;
+mod diagnostic;
+mod disasm;
+#[cfg(test)]
+mod fuzz;
+mod loader;
+mod machine;
+mod network;
+mod snapshot;
+mod threaded;
+
+pub use amplifier::{best_phase_settings, permutations, run_amplifiers, AmplifierError, Wiring};
+pub use ascii::{decode as decode_ascii, encode_line, AsciiMachine, Chunk};
+pub use asynchronous::{
+    channel, AsyncMachine, InputStream, OutputSink, Receiver, Sender, SinkClosed,
+};
+pub use cached::CachedMachine;
+pub use diagnostic::{run_diagnostic, DiagnosticError, DiagnosticReport, TestFailure};
+pub use disasm::{assemble, disassemble};
+pub use loader::{
+    decode_binary, encode_program, load_program, parse_program, parse_text, save_program, Format,
+    LoadError,
+};
+pub use machine::{decode, Decoded, Limits, Machine, MachineError, Mode, Opcode, Status};
+pub use network::{Network, NetworkError, Outcome, Packet, Topology};
+pub use threaded::{spawn, spawn_pipeline, Exit, Finished, MachineThread, ThreadError};
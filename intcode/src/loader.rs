@@ -0,0 +1,234 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Leading bytes of a binary snapshot, followed by a format version byte.
+const MAGIC: &[u8; 4] = b"INTC";
+const VERSION: u8 = 1;
+
+/// Text formats wrap after this many values per line.
+const VALUES_PER_LINE: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// All values on one line, separated by commas, as the puzzle inputs are.
+    Comma,
+    /// Values separated by spaces, wrapped over several lines.
+    Whitespace,
+    /// Magic, version, value count and zigzag encoded LEB128 varints.
+    Binary,
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    InvalidValue { line: usize, token: String },
+    InvalidBinary(&'static str),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Io(error) => write!(f, "{}", error),
+            LoadError::InvalidValue { line, token } => {
+                write!(f, "line {}: `{}` is not an integer", line, token)
+            }
+            LoadError::InvalidBinary(reason) => write!(f, "invalid binary program: {}", reason),
+        }
+    }
+}
+
+impl From<io::Error> for LoadError {
+    fn from(error: io::Error) -> Self {
+        LoadError::Io(error)
+    }
+}
+
+impl Format {
+    pub fn from_name(x: &str) -> Option<Format> {
+        match x {
+            "comma" => Some(Format::Comma),
+            "whitespace" => Some(Format::Whitespace),
+            "binary" => Some(Format::Binary),
+            _ => None,
+        }
+    }
+}
+
+/// Parses a text program. Values may be separated by commas, whitespace or
+/// both, spread over any number of lines, and `#` starts a comment.
+pub fn parse_text(content: &str) -> Result<Vec<i64>, LoadError> {
+    let mut program = vec![];
+    for (n, line) in content.lines().enumerate() {
+        let line = match line.find('#') {
+            Some(pos) => &line[..pos],
+            None => line,
+        };
+        for token in line.split(|c: char| c == ',' || c.is_whitespace()) {
+            if token.is_empty() {
+                continue;
+            }
+            let value = token.parse().map_err(|_| LoadError::InvalidValue {
+                line: n + 1,
+                token: token.to_string(),
+            })?;
+            program.push(value);
+        }
+    }
+    Ok(program)
+}
+
+pub fn decode_binary(bytes: &[u8]) -> Result<Vec<i64>, LoadError> {
+    if !bytes.starts_with(MAGIC) {
+        return Err(LoadError::InvalidBinary("missing header"));
+    }
+    match bytes.get(MAGIC.len()) {
+        Some(&VERSION) => {}
+        Some(_) => return Err(LoadError::InvalidBinary("unsupported version")),
+        None => return Err(LoadError::InvalidBinary("missing version")),
+    }
+    let mut rest = &bytes[MAGIC.len() + 1..];
+    let program = read_values(&mut rest)?;
+    if !rest.is_empty() {
+        return Err(LoadError::InvalidBinary("trailing bytes"));
+    }
+    Ok(program)
+}
+
+pub(crate) fn read_varint(bytes: &mut &[u8]) -> Result<u64, LoadError> {
+    let mut value: u64 = 0;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = bytes
+            .split_first()
+            .ok_or(LoadError::InvalidBinary("truncated program"))?;
+        *bytes = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(LoadError::InvalidBinary("varint too long"))
+}
+
+pub(crate) fn write_varint(output: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        output.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    output.push(value as u8);
+}
+
+/// Zigzag encoding keeps small negative numbers as short as positive ones.
+pub(crate) fn read_signed(bytes: &mut &[u8]) -> Result<i64, LoadError> {
+    let zigzag = read_varint(bytes)?;
+    Ok((zigzag >> 1) as i64 ^ -((zigzag & 1) as i64))
+}
+
+pub(crate) fn write_signed(output: &mut Vec<u8>, value: i64) {
+    write_varint(output, ((value << 1) ^ (value >> 63)) as u64);
+}
+
+/// A count followed by that many signed values.
+pub(crate) fn read_values(bytes: &mut &[u8]) -> Result<Vec<i64>, LoadError> {
+    let count = read_varint(bytes)?;
+    // Every value takes at least one byte, so a larger count is corrupt.
+    if count > bytes.len() as u64 {
+        return Err(LoadError::InvalidBinary("truncated program"));
+    }
+    (0..count).map(|_| read_signed(bytes)).collect()
+}
+
+pub(crate) fn write_values<'a>(
+    output: &mut Vec<u8>,
+    values: impl ExactSizeIterator<Item = &'a i64>,
+) {
+    write_varint(output, values.len() as u64);
+    for &value in values {
+        write_signed(output, value);
+    }
+}
+
+/// Parses either format, telling them apart by the binary header.
+pub fn parse_program(bytes: &[u8]) -> Result<Vec<i64>, LoadError> {
+    if bytes.starts_with(MAGIC) {
+        return decode_binary(bytes);
+    }
+    let content = std::str::from_utf8(bytes)
+        .map_err(|_| LoadError::InvalidBinary("neither text nor a binary program"))?;
+    parse_text(content)
+}
+
+pub fn load_program<P: AsRef<Path>>(path: P) -> Result<Vec<i64>, LoadError> {
+    parse_program(&fs::read(path)?)
+}
+
+pub fn encode_program(program: &[i64], format: Format) -> Vec<u8> {
+    let values: Vec<String> = program.iter().map(|x| x.to_string()).collect();
+    match format {
+        Format::Comma => format!("{}\n", values.join(",")).into_bytes(),
+        Format::Whitespace => {
+            let lines: Vec<String> = values
+                .chunks(VALUES_PER_LINE)
+                .map(|line| line.join(" ") + "\n")
+                .collect();
+            lines.concat().into_bytes()
+        }
+        Format::Binary => {
+            let mut output = MAGIC.to_vec();
+            output.push(VERSION);
+            write_values(&mut output, program.iter());
+            output
+        }
+    }
+}
+
+pub fn save_program<P: AsRef<Path>>(
+    path: P,
+    program: &[i64],
+    format: Format,
+) -> Result<(), LoadError> {
+    fs::write(path, encode_program(program, format))?;
+    Ok(())
+}
+
+#[test]
+fn test_parse_text() {
+    assert_eq!(vec![1, 0, 0, 3, 99], parse_text("1,0,0,3,99\n").unwrap());
+    assert_eq!(
+        vec![1002, 4, 3, 4, -33, 99],
+        parse_text("# multiply\n1002, 4 3 4\n\t-33 # operand\n\n99").unwrap()
+    );
+    match parse_text("1,2\n3,x,4") {
+        Err(LoadError::InvalidValue { line, token }) => {
+            assert_eq!(2, line);
+            assert_eq!("x", token);
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn test_round_trip_formats() {
+    let program: Vec<i64> = (-40..40)
+        .map(|x| x * x * x * 1_000_003)
+        .chain(vec![i64::MIN, i64::MAX])
+        .collect();
+    for &format in &[Format::Comma, Format::Whitespace, Format::Binary] {
+        let bytes = encode_program(&program, format);
+        assert_eq!(program, parse_program(&bytes).unwrap(), "{:?}", format);
+    }
+    let binary = encode_program(&[1, -1, 99], Format::Binary);
+    assert_eq!(b"INTC\x01\x03\x02\x01\xc6\x01".to_vec(), binary);
+}
+
+#[test]
+fn test_corrupt_binary() {
+    let binary = encode_program(&[1, 2, 300], Format::Binary);
+    for end in MAGIC.len()..binary.len() {
+        assert!(decode_binary(&binary[..end]).is_err(), "prefix of {}", end);
+    }
+    let mut trailing = binary.clone();
+    trailing.push(0);
+    assert!(decode_binary(&trailing).is_err());
+}
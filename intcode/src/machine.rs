@@ -0,0 +1,496 @@
+use std::collections::VecDeque;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    Position,
+    Immediate,
+    Relative,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Opcode {
+    Add,
+    Multiply,
+    Input,
+    Output,
+    JumpIfTrue,
+    JumpIfFalse,
+    LessThan,
+    Equals,
+    AdjustRelativeBase,
+    Halt,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decoded {
+    pub opcode: Opcode,
+    pub modes: [Mode; 3],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    /// The instruction executed and the machine can keep going.
+    Running,
+    /// The next instruction is an `Input` and the input queue is empty.
+    NeedsInput,
+    Halted,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MachineError {
+    UnknownOpcode {
+        ip: usize,
+        value: i64,
+    },
+    UnknownMode {
+        ip: usize,
+        value: i64,
+    },
+    ImmediateWrite {
+        ip: usize,
+    },
+    InvalidAddress {
+        ip: usize,
+        address: i64,
+    },
+    Overflow {
+        ip: usize,
+    },
+    StepLimit {
+        limit: u64,
+    },
+    MemoryLimit {
+        ip: usize,
+        address: usize,
+        limit: usize,
+    },
+    OutputLimit {
+        ip: usize,
+        limit: usize,
+    },
+    /// Growing memory to reach `address` failed to allocate.
+    OutOfMemory {
+        ip: usize,
+        address: usize,
+    },
+}
+
+impl fmt::Display for MachineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MachineError::UnknownOpcode { ip, value } => {
+                write!(f, "unknown opcode {} at {}", value, ip)
+            }
+            MachineError::UnknownMode { ip, value } => {
+                write!(f, "unknown parameter mode in {} at {}", value, ip)
+            }
+            MachineError::ImmediateWrite { ip } => {
+                write!(f, "write to an immediate parameter at {}", ip)
+            }
+            MachineError::InvalidAddress { ip, address } => {
+                write!(f, "invalid address {} at {}", address, ip)
+            }
+            MachineError::Overflow { ip } => write!(f, "arithmetic overflow at {}", ip),
+            MachineError::StepLimit { limit } => write!(f, "step limit of {} reached", limit),
+            MachineError::MemoryLimit { ip, address, limit } => write!(
+                f,
+                "write to {} at {} exceeds the memory limit of {}",
+                address, ip, limit
+            ),
+            MachineError::OutputLimit { ip, limit } => {
+                write!(f, "output at {} exceeds the output limit of {}", ip, limit)
+            }
+            MachineError::OutOfMemory { ip, address } => {
+                write!(f, "out of memory writing to {} at {}", address, ip)
+            }
+        }
+    }
+}
+
+impl Opcode {
+    pub fn from_i64(x: i64) -> Option<Opcode> {
+        match x {
+            1 => Some(Opcode::Add),
+            2 => Some(Opcode::Multiply),
+            3 => Some(Opcode::Input),
+            4 => Some(Opcode::Output),
+            5 => Some(Opcode::JumpIfTrue),
+            6 => Some(Opcode::JumpIfFalse),
+            7 => Some(Opcode::LessThan),
+            8 => Some(Opcode::Equals),
+            9 => Some(Opcode::AdjustRelativeBase),
+            99 => Some(Opcode::Halt),
+            _ => None,
+        }
+    }
+
+    pub fn parameter_count(self) -> usize {
+        match self {
+            Opcode::Add | Opcode::Multiply | Opcode::LessThan | Opcode::Equals => 3,
+            Opcode::JumpIfTrue | Opcode::JumpIfFalse => 2,
+            Opcode::Input | Opcode::Output | Opcode::AdjustRelativeBase => 1,
+            Opcode::Halt => 0,
+        }
+    }
+}
+
+impl Mode {
+    fn from_i64(x: i64) -> Option<Mode> {
+        match x {
+            0 => Some(Mode::Position),
+            1 => Some(Mode::Immediate),
+            2 => Some(Mode::Relative),
+            _ => None,
+        }
+    }
+}
+
+/// Splits an instruction value into its opcode and the modes of its three
+/// parameters, with the same digit layout as day-5's `get_operator`.
+pub fn decode(value: i64, ip: usize) -> Result<Decoded, MachineError> {
+    if value < 0 {
+        return Err(MachineError::UnknownOpcode { ip, value });
+    }
+    let opcode = Opcode::from_i64(value % 100).ok_or(MachineError::UnknownOpcode { ip, value })?;
+    let mut modes = [Mode::Position; 3];
+    let mut digits = value / 100;
+    for mode in modes.iter_mut() {
+        *mode = Mode::from_i64(digits % 10).ok_or(MachineError::UnknownMode { ip, value })?;
+        digits /= 10;
+    }
+    if digits != 0 {
+        return Err(MachineError::UnknownMode { ip, value });
+    }
+    Ok(Decoded { opcode, modes })
+}
+
+/// Bounds for running untrusted programs, `None` meaning unlimited.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Limits {
+    /// Instructions the machine may execute in total.
+    pub max_steps: Option<u64>,
+    /// Memory cells the machine may grow to.
+    pub max_memory: Option<usize>,
+    /// Values the machine may output in total.
+    pub max_outputs: Option<usize>,
+}
+
+/// An Intcode computer whose whole state lives in the struct, so it can be
+/// paused whenever it needs input and resumed later.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Machine {
+    pub(crate) memory: Vec<i64>,
+    pub(crate) ip: usize,
+    pub(crate) relative_base: i64,
+    pub(crate) input: VecDeque<i64>,
+    pub(crate) output: VecDeque<i64>,
+    pub(crate) steps: u64,
+    pub(crate) outputs: usize,
+    pub(crate) limits: Limits,
+}
+
+impl Machine {
+    pub fn new(program: Vec<i64>) -> Machine {
+        Machine {
+            memory: program,
+            ip: 0,
+            relative_base: 0,
+            input: VecDeque::new(),
+            output: VecDeque::new(),
+            steps: 0,
+            outputs: 0,
+            limits: Limits::default(),
+        }
+    }
+
+    pub fn with_limits(mut self, limits: Limits) -> Machine {
+        self.limits = limits;
+        self
+    }
+
+    /// Instructions executed so far.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    pub fn memory(&self) -> &[i64] {
+        &self.memory
+    }
+
+    pub fn ip(&self) -> usize {
+        self.ip
+    }
+
+    pub fn relative_base(&self) -> i64 {
+        self.relative_base
+    }
+
+    pub fn push_input(&mut self, value: i64) {
+        self.input.push_back(value);
+    }
+
+    pub fn pending_input(&self) -> usize {
+        self.input.len()
+    }
+
+    pub fn pop_output(&mut self) -> Option<i64> {
+        self.output.pop_front()
+    }
+
+    pub fn take_output(&mut self) -> Vec<i64> {
+        self.output.drain(..).collect()
+    }
+
+    /// Value at `address`, memory past the end of the program reads as zero.
+    pub fn read(&self, address: usize) -> i64 {
+        self.memory.get(address).copied().unwrap_or(0)
+    }
+
+    /// Stores `value`, growing memory with zeroes when writing past the end.
+    /// Fails when that would go over the memory limit, or when memory cannot
+    /// grow that far.
+    fn store(&mut self, address: usize, value: i64) -> Result<(), MachineError> {
+        if let Some(limit) = self.limits.max_memory {
+            if address >= limit {
+                return Err(MachineError::MemoryLimit {
+                    ip: self.ip,
+                    address,
+                    limit,
+                });
+            }
+        }
+        if address >= self.memory.len() {
+            self.memory
+                .try_reserve_exact(address + 1 - self.memory.len())
+                .map_err(|_| MachineError::OutOfMemory {
+                    ip: self.ip,
+                    address,
+                })?;
+            self.memory.resize(address + 1, 0);
+        }
+        self.memory[address] = value;
+        Ok(())
+    }
+
+    fn address(&self, parameter: usize, mode: Mode) -> Result<usize, MachineError> {
+        let value = self.read(self.ip + parameter);
+        let address = match mode {
+            Mode::Position => value,
+            Mode::Relative => self
+                .relative_base
+                .checked_add(value)
+                .ok_or(MachineError::Overflow { ip: self.ip })?,
+            Mode::Immediate => return Err(MachineError::ImmediateWrite { ip: self.ip }),
+        };
+        if address < 0 {
+            return Err(MachineError::InvalidAddress {
+                ip: self.ip,
+                address,
+            });
+        }
+        Ok(address as usize)
+    }
+
+    fn parameter(&self, decoded: &Decoded, parameter: usize) -> Result<i64, MachineError> {
+        match decoded.modes[parameter - 1] {
+            Mode::Immediate => Ok(self.read(self.ip + parameter)),
+            mode => Ok(self.read(self.address(parameter, mode)?)),
+        }
+    }
+
+    fn jump_target(&self, value: i64) -> Result<usize, MachineError> {
+        if value < 0 {
+            return Err(MachineError::InvalidAddress {
+                ip: self.ip,
+                address: value,
+            });
+        }
+        Ok(value as usize)
+    }
+
+    pub(crate) fn check_step_limit(&self) -> Result<(), MachineError> {
+        match self.limits.max_steps {
+            Some(limit) if self.steps >= limit => Err(MachineError::StepLimit { limit }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Executes a single instruction. Nothing changes when the machine is
+    /// halted or waiting for input.
+    pub fn step(&mut self) -> Result<Status, MachineError> {
+        self.check_step_limit()?;
+        let decoded = decode(self.read(self.ip), self.ip)?;
+        self.execute(decoded)
+    }
+
+    /// Executes the instruction at `ip`, already decoded by the caller.
+    pub(crate) fn execute(&mut self, decoded: Decoded) -> Result<Status, MachineError> {
+        let overflow = MachineError::Overflow { ip: self.ip };
+        let next = self.ip + 1 + decoded.opcode.parameter_count();
+
+        match decoded.opcode {
+            Opcode::Add | Opcode::Multiply | Opcode::LessThan | Opcode::Equals => {
+                let left = self.parameter(&decoded, 1)?;
+                let right = self.parameter(&decoded, 2)?;
+                let address = self.address(3, decoded.modes[2])?;
+                let result = match decoded.opcode {
+                    Opcode::Add => left.checked_add(right).ok_or(overflow)?,
+                    Opcode::Multiply => left.checked_mul(right).ok_or(overflow)?,
+                    Opcode::LessThan => (left < right) as i64,
+                    _ => (left == right) as i64,
+                };
+                self.store(address, result)?;
+                self.ip = next;
+            }
+            Opcode::Input => {
+                let address = self.address(1, decoded.modes[0])?;
+                match self.input.pop_front() {
+                    Some(value) => self.store(address, value)?,
+                    None => return Ok(Status::NeedsInput),
+                }
+                self.ip = next;
+            }
+            Opcode::Output => {
+                let value = self.parameter(&decoded, 1)?;
+                if let Some(limit) = self.limits.max_outputs {
+                    if self.outputs >= limit {
+                        return Err(MachineError::OutputLimit { ip: self.ip, limit });
+                    }
+                }
+                self.output.push_back(value);
+                self.outputs += 1;
+                self.ip = next;
+            }
+            Opcode::JumpIfTrue | Opcode::JumpIfFalse => {
+                let condition = self.parameter(&decoded, 1)?;
+                let target = self.parameter(&decoded, 2)?;
+                if (condition != 0) == (decoded.opcode == Opcode::JumpIfTrue) {
+                    self.ip = self.jump_target(target)?;
+                } else {
+                    self.ip = next;
+                }
+            }
+            Opcode::AdjustRelativeBase => {
+                let offset = self.parameter(&decoded, 1)?;
+                self.relative_base = self.relative_base.checked_add(offset).ok_or(overflow)?;
+                self.ip = next;
+            }
+            Opcode::Halt => return Ok(Status::Halted),
+        }
+        self.steps += 1;
+        Ok(Status::Running)
+    }
+
+    /// Runs until the machine halts or needs input it does not have.
+    pub fn run(&mut self) -> Result<Status, MachineError> {
+        loop {
+            match self.step()? {
+                Status::Running => {}
+                status => return Ok(status),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_decode() {
+    assert_eq!(
+        Ok(Decoded {
+            opcode: Opcode::Multiply,
+            modes: [Mode::Position, Mode::Immediate, Mode::Position],
+        }),
+        decode(1002, 0)
+    );
+    assert_eq!(Opcode::AdjustRelativeBase, decode(209, 0).unwrap().opcode);
+    assert_eq!(
+        Err(MachineError::UnknownOpcode { ip: 4, value: 42 }),
+        decode(42, 4)
+    );
+    assert_eq!(
+        Err(MachineError::UnknownMode { ip: 0, value: 301 }),
+        decode(301, 0)
+    );
+}
+
+#[test]
+fn test_run_day_5_examples() {
+    // Outputs 999, 1000 or 1001 for inputs below, equal to or above 8.
+    let program = vec![
+        3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31, 1106, 0, 36, 98, 0, 0,
+        1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104, 999, 1105, 1, 46, 1101, 1000, 1, 20, 4, 20,
+        1105, 1, 46, 98, 99,
+    ];
+    for &(input, expected) in &[(7, 999), (8, 1000), (9, 1001)] {
+        let mut machine = Machine::new(program.clone());
+        assert_eq!(Ok(Status::NeedsInput), machine.run());
+        machine.push_input(input);
+        assert_eq!(Ok(Status::Halted), machine.run());
+        assert_eq!(vec![expected], machine.take_output());
+    }
+}
+
+#[test]
+fn test_relative_mode_and_large_memory() {
+    // Copies itself to the output.
+    let quine = vec![
+        109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+    ];
+    let mut machine = Machine::new(quine.clone());
+    assert_eq!(Ok(Status::Halted), machine.run());
+    assert_eq!(quine, machine.take_output());
+
+    let mut machine = Machine::new(vec![104, 1_125_899_906_842_624, 99]);
+    machine.run().unwrap();
+    assert_eq!(Some(1_125_899_906_842_624), machine.pop_output());
+}
+
+#[test]
+fn test_limits() {
+    let forever = vec![1105, 1, 0];
+    let limits = Limits {
+        max_steps: Some(100),
+        ..Limits::default()
+    };
+    let mut machine = Machine::new(forever).with_limits(limits);
+    assert_eq!(Err(MachineError::StepLimit { limit: 100 }), machine.run());
+    assert_eq!(100, machine.steps());
+
+    let far_write = vec![1101, 1, 1, 1_000_000, 99];
+    let limits = Limits {
+        max_memory: Some(4096),
+        ..Limits::default()
+    };
+    assert_eq!(
+        Err(MachineError::MemoryLimit {
+            ip: 0,
+            address: 1_000_000,
+            limit: 4096
+        }),
+        Machine::new(far_write).with_limits(limits).run()
+    );
+
+    let chatty = vec![104, 7, 1105, 1, 0];
+    let limits = Limits {
+        max_outputs: Some(3),
+        ..Limits::default()
+    };
+    let mut machine = Machine::new(chatty).with_limits(limits);
+    assert_eq!(
+        Err(MachineError::OutputLimit { ip: 0, limit: 3 }),
+        machine.run()
+    );
+    assert_eq!(vec![7, 7, 7], machine.take_output());
+}
+
+#[test]
+fn test_unlimited_memory_fails_cleanly() {
+    let huge_write = vec![1101, 1, 1, i64::MAX - 1, 99];
+    assert_eq!(
+        Err(MachineError::OutOfMemory {
+            ip: 0,
+            address: (i64::MAX - 1) as usize
+        }),
+        Machine::new(huge_write).run()
+    );
+}
@@ -0,0 +1,373 @@
+use std::fmt;
+use std::num::NonZeroUsize;
+
+use crate::{Machine, MachineError, Status};
+
+/// Steps each machine may run before the scheduler moves on to the next one.
+const DEFAULT_TIME_SLICE: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Topology {
+    /// Each machine feeds the next one, the last one feeds the outside.
+    Pipeline,
+    /// Like a pipeline, but the last machine feeds the first.
+    Ring,
+    /// Every output goes to all the other machines.
+    Broadcast,
+    /// Machines send packets of `packet_size` values, the first of which is
+    /// the destination address. Unknown addresses leave the network.
+    Addressed { packet_size: NonZeroUsize },
+}
+
+/// Values sent from one machine to another, or out of the network.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Packet {
+    pub source: usize,
+    pub destination: i64,
+    pub values: Vec<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    /// Every machine has halted.
+    Halted,
+    /// The machines at these addresses wait for input nobody will send.
+    Deadlocked { waiting: Vec<usize> },
+    /// All queues are empty and every running machine is polling with the
+    /// idle input value.
+    Idle,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkError {
+    pub address: usize,
+    pub error: MachineError,
+}
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "machine {}: {}", self.address, self.error)
+    }
+}
+
+#[derive(Debug)]
+struct Node {
+    machine: Machine,
+    halted: bool,
+    /// Its last slice ended with it asking for input again right after
+    /// reading the idle value, and it has not sent or received anything since.
+    idle: bool,
+    pending: Vec<i64>,
+    last_output: Option<i64>,
+}
+
+/// Runs many machines cooperatively on one thread, round robin, routing
+/// outputs to inputs according to the topology.
+#[derive(Debug)]
+pub struct Network {
+    nodes: Vec<Node>,
+    topology: Topology,
+    time_slice: usize,
+    idle_input: Option<i64>,
+    delivered: Vec<Packet>,
+}
+
+impl Network {
+    pub fn new(machines: Vec<Machine>, topology: Topology) -> Network {
+        Network {
+            nodes: machines
+                .into_iter()
+                .map(|machine| Node {
+                    machine,
+                    halted: false,
+                    idle: false,
+                    pending: vec![],
+                    last_output: None,
+                })
+                .collect(),
+            topology,
+            time_slice: DEFAULT_TIME_SLICE,
+            idle_input: None,
+            delivered: vec![],
+        }
+    }
+
+    /// Machines waiting on an empty queue read `value` instead of blocking,
+    /// which turns deadlocks into `Outcome::Idle`.
+    pub fn with_idle_input(mut self, value: i64) -> Network {
+        self.idle_input = Some(value);
+        self
+    }
+
+    pub fn with_time_slice(mut self, steps: usize) -> Network {
+        self.time_slice = steps.max(1);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn machine(&self, address: usize) -> &Machine {
+        &self.nodes[address].machine
+    }
+
+    /// Most recent value the machine at `address` has output.
+    pub fn last_output(&self, address: usize) -> Option<i64> {
+        self.nodes[address].last_output
+    }
+
+    /// Queues an input for a machine from outside the network.
+    pub fn send(&mut self, address: usize, value: i64) {
+        let node = &mut self.nodes[address];
+        node.machine.push_input(value);
+        node.idle = false;
+    }
+
+    /// Packets that have left the network since the last call.
+    pub fn take_output(&mut self) -> Vec<Packet> {
+        self.delivered.drain(..).collect()
+    }
+
+    /// Runs every machine for one time slice. Returns whether any of them
+    /// executed an instruction.
+    pub fn run_round(&mut self) -> Result<bool, NetworkError> {
+        let mut progress = false;
+        for address in 0..self.nodes.len() {
+            progress |= self.run_slice(address)?;
+            self.route(address);
+        }
+        Ok(progress)
+    }
+
+    /// Runs rounds until every machine halts, deadlocks or idles.
+    pub fn run(&mut self) -> Result<Outcome, NetworkError> {
+        loop {
+            let progress = self.run_round()?;
+            if self.nodes.iter().all(|node| node.halted) {
+                return Ok(Outcome::Halted);
+            }
+            if !progress {
+                let waiting = (0..self.nodes.len())
+                    .filter(|&address| !self.nodes[address].halted)
+                    .collect();
+                return Ok(Outcome::Deadlocked { waiting });
+            }
+            if self.is_idle() {
+                return Ok(Outcome::Idle);
+            }
+        }
+    }
+
+    fn is_idle(&self) -> bool {
+        self.idle_input.is_some()
+            && self
+                .nodes
+                .iter()
+                .all(|node| node.halted || (node.idle && node.pending.is_empty()))
+    }
+
+    fn run_slice(&mut self, address: usize) -> Result<bool, NetworkError> {
+        let idle_input = self.idle_input;
+        let node = &mut self.nodes[address];
+        let mut progress = false;
+        let mut polled = false;
+        node.idle = false;
+        for _ in 0..self.time_slice {
+            match node
+                .machine
+                .step()
+                .map_err(|error| NetworkError { address, error })?
+            {
+                Status::Running => progress = true,
+                Status::NeedsInput => match idle_input {
+                    // Asking again after an idle value means it is only polling.
+                    Some(_) if polled => {
+                        node.idle = true;
+                        break;
+                    }
+                    Some(value) => {
+                        node.machine.push_input(value);
+                        polled = true;
+                    }
+                    None => break,
+                },
+                Status::Halted => {
+                    node.halted = true;
+                    break;
+                }
+            }
+        }
+        Ok(progress)
+    }
+
+    fn route(&mut self, source: usize) {
+        let count = self.nodes.len();
+        let outputs = self.nodes[source].machine.take_output();
+        if let Some(&last) = outputs.last() {
+            self.nodes[source].idle = false;
+            self.nodes[source].last_output = Some(last);
+        }
+        for value in outputs {
+            match self.topology {
+                Topology::Pipeline if source + 1 == count => {
+                    self.deliver(source, count as i64, vec![value])
+                }
+                Topology::Pipeline | Topology::Ring => self.send((source + 1) % count, value),
+                Topology::Broadcast if count == 1 => self.deliver(source, 1, vec![value]),
+                Topology::Broadcast => {
+                    for address in (0..count).filter(|&address| address != source) {
+                        self.send(address, value);
+                    }
+                }
+                Topology::Addressed { packet_size } => {
+                    let pending = &mut self.nodes[source].pending;
+                    pending.push(value);
+                    if pending.len() < packet_size.get() {
+                        continue;
+                    }
+                    let mut values = std::mem::take(pending);
+                    let destination = values.remove(0);
+                    if destination >= 0 && (destination as usize) < count {
+                        for value in values {
+                            self.send(destination as usize, value);
+                        }
+                    } else {
+                        self.deliver(source, destination, values);
+                    }
+                }
+            }
+        }
+    }
+
+    fn deliver(&mut self, source: usize, destination: i64, values: Vec<i64>) {
+        self.delivered.push(Packet {
+            source,
+            destination,
+            values,
+        });
+    }
+}
+
+#[cfg(test)]
+const ADD_ONE_FOREVER: [i64; 11] = [3, 20, 1001, 20, 1, 20, 4, 20, 1105, 1, 0];
+
+#[cfg(test)]
+const ADD_ONE_ONCE: [i64; 9] = [3, 9, 1001, 9, 1, 9, 4, 9, 99];
+
+#[test]
+fn test_pipeline() {
+    let machines = vec![Machine::new(ADD_ONE_FOREVER.to_vec()); 3];
+    let mut network = Network::new(machines, Topology::Pipeline).with_time_slice(2);
+    network.send(0, 5);
+    network.send(0, 10);
+    assert_eq!(
+        Ok(Outcome::Deadlocked {
+            waiting: vec![0, 1, 2]
+        }),
+        network.run()
+    );
+    let values: Vec<i64> = network
+        .take_output()
+        .into_iter()
+        .flat_map(|packet| packet.values)
+        .collect();
+    assert_eq!(vec![8, 13], values);
+}
+
+#[test]
+fn test_ring_and_broadcast() {
+    let mut ring = Network::new(vec![Machine::new(ADD_ONE_ONCE.to_vec()); 3], Topology::Ring);
+    ring.send(0, 0);
+    assert_eq!(Ok(Outcome::Halted), ring.run());
+    assert_eq!(1, ring.machine(0).pending_input());
+
+    let stuck = Network::new(vec![Machine::new(ADD_ONE_ONCE.to_vec()); 2], Topology::Ring).run();
+    assert_eq!(
+        Ok(Outcome::Deadlocked {
+            waiting: vec![0, 1]
+        }),
+        stuck
+    );
+
+    // 0 sends 2 to both others, which each read it and send 3 to the rest.
+    let mut broadcast = Network::new(
+        vec![Machine::new(ADD_ONE_ONCE.to_vec()); 3],
+        Topology::Broadcast,
+    );
+    broadcast.send(0, 1);
+    assert_eq!(Ok(Outcome::Halted), broadcast.run());
+    assert_eq!(2, broadcast.machine(0).pending_input());
+    assert_eq!(1, broadcast.machine(1).pending_input());
+    assert_eq!(1, broadcast.machine(2).pending_input());
+}
+
+#[test]
+fn test_addressed_packets_and_idle() {
+    // Reads its address, then forwards every non-idle input to address 255.
+    let forwarder = vec![
+        3, 30, 3, 31, 1008, 31, -1, 32, 1005, 32, 2, 104, 255, 4, 31, 1105, 1, 2,
+    ];
+    let machines = vec![Machine::new(forwarder); 2];
+    let mut network = Network::new(
+        machines,
+        Topology::Addressed {
+            packet_size: NonZeroUsize::new(2).unwrap(),
+        },
+    )
+    .with_idle_input(-1);
+    network.send(0, 0);
+    network.send(1, 1);
+    network.send(1, 7);
+    assert_eq!(Ok(Outcome::Idle), network.run());
+    assert_eq!(
+        vec![Packet {
+            source: 1,
+            destination: 255,
+            values: vec![7],
+        }],
+        network.take_output()
+    );
+}
+
+#[test]
+fn test_machine_errors_carry_address() {
+    let machines = vec![Machine::new(vec![99]), Machine::new(vec![42])];
+    let error = Network::new(machines, Topology::Pipeline)
+        .run()
+        .unwrap_err();
+    assert_eq!(1, error.address);
+    assert_eq!(
+        MachineError::UnknownOpcode { ip: 0, value: 42 },
+        error.error
+    );
+}
+
+#[test]
+fn test_busy_machine_is_not_idle() {
+    // Reads one value, counts to 3000 over several time slices, then sends
+    // 7 to address 255.
+    let program = vec![
+        3, 100, 1001, 101, 1, 101, 1007, 101, 3000, 102, 1005, 102, 2, 104, 255, 104, 7, 99,
+    ];
+    let mut network = Network::new(
+        vec![Machine::new(program)],
+        Topology::Addressed {
+            packet_size: NonZeroUsize::new(2).unwrap(),
+        },
+    )
+    .with_idle_input(-1);
+    assert_eq!(Ok(Outcome::Halted), network.run());
+    assert_eq!(
+        vec![Packet {
+            source: 0,
+            destination: 255,
+            values: vec![7],
+        }],
+        network.take_output()
+    );
+}
@@ -0,0 +1,128 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::path::Path;
+
+use crate::loader::{
+    read_signed, read_values, read_varint, write_signed, write_values, write_varint,
+};
+use crate::{Limits, LoadError, Machine};
+
+/// Leading bytes of a machine snapshot, followed by a format version byte.
+const MAGIC: &[u8; 4] = b"INTS";
+/// Version 2 added the step and output counters.
+const VERSION: u8 = 2;
+
+impl Machine {
+    /// Serializes the full machine state: instruction pointer, relative base,
+    /// step and output counters, memory and both queues, using the varints of
+    /// the binary program format. Limits are settings rather than state and
+    /// are not included.
+    pub fn snapshot(&self) -> Vec<u8> {
+        let mut output = MAGIC.to_vec();
+        output.push(VERSION);
+        write_varint(&mut output, self.ip as u64);
+        write_signed(&mut output, self.relative_base);
+        write_varint(&mut output, self.steps);
+        write_varint(&mut output, self.outputs as u64);
+        write_values(&mut output, self.memory.iter());
+        write_values(&mut output, self.input.iter());
+        write_values(&mut output, self.output.iter());
+        output
+    }
+
+    pub fn restore(bytes: &[u8]) -> Result<Machine, LoadError> {
+        if !bytes.starts_with(MAGIC) {
+            return Err(LoadError::InvalidBinary("missing snapshot header"));
+        }
+        let version = match bytes.get(MAGIC.len()) {
+            Some(&version) if version == 1 || version == VERSION => version,
+            Some(_) => return Err(LoadError::InvalidBinary("unsupported snapshot version")),
+            None => return Err(LoadError::InvalidBinary("missing snapshot version")),
+        };
+        let mut rest = &bytes[MAGIC.len() + 1..];
+        let ip = read_varint(&mut rest)?;
+        let relative_base = read_signed(&mut rest)?;
+        let (steps, outputs) = match version {
+            1 => (0, 0),
+            _ => (read_varint(&mut rest)?, read_varint(&mut rest)? as usize),
+        };
+        let memory = read_values(&mut rest)?;
+        let input: VecDeque<i64> = read_values(&mut rest)?.into();
+        let output: VecDeque<i64> = read_values(&mut rest)?.into();
+        if !rest.is_empty() {
+            return Err(LoadError::InvalidBinary("trailing bytes"));
+        }
+        if ip > memory.len() as u64 {
+            return Err(LoadError::InvalidBinary(
+                "instruction pointer outside memory",
+            ));
+        }
+        Ok(Machine {
+            memory,
+            ip: ip as usize,
+            relative_base,
+            input,
+            output,
+            steps,
+            outputs,
+            limits: Limits::default(),
+        })
+    }
+
+    pub fn save_snapshot<P: AsRef<Path>>(&self, path: P) -> Result<(), LoadError> {
+        fs::write(path, self.snapshot())?;
+        Ok(())
+    }
+
+    pub fn load_snapshot<P: AsRef<Path>>(path: P) -> Result<Machine, LoadError> {
+        Machine::restore(&fs::read(path)?)
+    }
+}
+
+#[test]
+fn test_snapshot_mid_run() {
+    use crate::Status;
+
+    // Reads two numbers, outputs their sum, then their product.
+    let program = vec![
+        3, 100, 3, 101, 1, 100, 101, 102, 4, 102, 2, 100, 101, 102, 4, 102, 99,
+    ];
+    let mut machine = Machine::new(program);
+    machine.push_input(6);
+    assert_eq!(Ok(Status::NeedsInput), machine.run());
+    machine.push_input(7);
+    machine.step().unwrap();
+    machine.step().unwrap();
+    machine.step().unwrap();
+
+    let mut restored = Machine::restore(&machine.snapshot()).unwrap();
+    assert_eq!(machine, restored);
+    assert_eq!(Ok(Status::Halted), restored.run());
+    assert_eq!(vec![13, 42], restored.take_output());
+}
+
+#[test]
+fn test_restore_rejects_corrupt_snapshots() {
+    let mut machine = Machine::new(vec![109, -5, 3, 0, 99]);
+    machine.push_input(1);
+    machine.step().unwrap();
+    let snapshot = machine.snapshot();
+    for end in 0..snapshot.len() {
+        assert!(
+            Machine::restore(&snapshot[..end]).is_err(),
+            "prefix of {}",
+            end
+        );
+    }
+    let program = crate::encode_program(&[99], crate::Format::Binary);
+    assert!(Machine::restore(&program).is_err());
+}
+
+#[test]
+fn test_restore_version_1() {
+    let snapshot = b"INTS\x01\x02\x00\x02\xc6\x01\x00\x00\x00";
+    let machine = Machine::restore(snapshot).unwrap();
+    assert_eq!(&[99, 0], machine.memory());
+    assert_eq!(2, machine.ip());
+    assert_eq!(0, machine.steps());
+}
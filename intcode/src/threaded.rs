@@ -0,0 +1,189 @@
+use std::fmt;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::{Machine, MachineError, Status};
+
+/// Why a machine thread stopped without an error.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Exit {
+    Halted,
+    /// It needed input, but every sender for its input channel was gone.
+    InputClosed,
+    /// It produced output, but the receiving end had been dropped.
+    OutputClosed,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ThreadError {
+    Machine(MachineError),
+    /// No input arrived within the timeout while waiting at `ip`.
+    InputTimeout {
+        ip: usize,
+    },
+}
+
+impl fmt::Display for ThreadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ThreadError::Machine(error) => write!(f, "{}", error),
+            ThreadError::InputTimeout { ip } => write!(f, "timed out waiting for input at {}", ip),
+        }
+    }
+}
+
+/// The machine as it was when its thread stopped.
+#[derive(Debug)]
+pub struct Finished {
+    pub machine: Machine,
+    pub exit: Exit,
+    /// Most recent value the machine output, even if it could not be sent.
+    pub last_output: Option<i64>,
+}
+
+pub type MachineThread = JoinHandle<Result<Finished, ThreadError>>;
+
+/// Runs `machine` on its own thread, reading from `input` whenever it needs
+/// a value and sending every output on `output`. The thread ends cleanly when
+/// the machine halts or a peer on either channel has gone away.
+pub fn spawn(
+    mut machine: Machine,
+    input: Receiver<i64>,
+    output: Sender<i64>,
+    timeout: Option<Duration>,
+) -> MachineThread {
+    thread::spawn(move || {
+        let mut last_output = None;
+        let exit = loop {
+            let status = machine.step().map_err(ThreadError::Machine)?;
+            let mut closed = false;
+            while let Some(value) = machine.pop_output() {
+                last_output = Some(value);
+                if output.send(value).is_err() {
+                    closed = true;
+                    break;
+                }
+            }
+            if closed {
+                break Exit::OutputClosed;
+            }
+            match status {
+                Status::Running => {}
+                Status::Halted => break Exit::Halted,
+                Status::NeedsInput => {
+                    let value = match timeout {
+                        Some(timeout) => match input.recv_timeout(timeout) {
+                            Ok(value) => value,
+                            Err(RecvTimeoutError::Timeout) => {
+                                return Err(ThreadError::InputTimeout { ip: machine.ip() })
+                            }
+                            Err(RecvTimeoutError::Disconnected) => break Exit::InputClosed,
+                        },
+                        None => match input.recv() {
+                            Ok(value) => value,
+                            Err(_) => break Exit::InputClosed,
+                        },
+                    };
+                    machine.push_input(value);
+                }
+            }
+        };
+        Ok(Finished {
+            machine,
+            exit,
+            last_output,
+        })
+    })
+}
+
+/// Spawns the machines in a chain, each one's output feeding the next one's
+/// input. Returns the sender into the first machine, the receiver from the
+/// last one and the thread handles in chain order.
+pub fn spawn_pipeline(
+    machines: Vec<Machine>,
+    timeout: Option<Duration>,
+) -> (Sender<i64>, Receiver<i64>, Vec<MachineThread>) {
+    let (first, mut input) = mpsc::channel();
+    let mut handles = vec![];
+    for machine in machines {
+        let (output, next) = mpsc::channel();
+        handles.push(spawn(machine, input, output, timeout));
+        input = next;
+    }
+    (first, input, handles)
+}
+
+#[test]
+fn test_pipeline_threads() {
+    let add_one = vec![3, 20, 1001, 20, 1, 20, 4, 20, 1105, 1, 0];
+    let (sender, receiver, handles) = spawn_pipeline(vec![Machine::new(add_one); 4], None);
+    for value in 0..5 {
+        sender.send(value).unwrap();
+    }
+    let outputs: Vec<i64> = receiver.iter().take(5).collect();
+    assert_eq!(vec![4, 5, 6, 7, 8], outputs);
+
+    // Closing the input shuts every machine down in turn.
+    drop(sender);
+    for handle in handles {
+        let finished = handle.join().unwrap().unwrap();
+        assert_eq!(Exit::InputClosed, finished.exit);
+    }
+}
+
+#[test]
+fn test_feedback_threads() {
+    let program = vec![
+        3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1, 28,
+        1005, 28, 6, 99, 0, 0, 5,
+    ];
+    let phases = [9, 8, 7, 6, 5];
+    let channels: Vec<(Sender<i64>, Receiver<i64>)> =
+        phases.iter().map(|_| mpsc::channel()).collect();
+    let mut senders: Vec<Sender<i64>> = channels.iter().map(|(sender, _)| sender.clone()).collect();
+    for (sender, &phase) in senders.iter().zip(phases.iter()) {
+        sender.send(phase).unwrap();
+    }
+    senders[0].send(0).unwrap();
+    senders.rotate_left(1);
+
+    let handles: Vec<_> = channels
+        .into_iter()
+        .zip(senders)
+        .map(|((_, input), output)| spawn(Machine::new(program.clone()), input, output, None))
+        .collect();
+    let results: Vec<Finished> = handles
+        .into_iter()
+        .map(|handle| handle.join().unwrap().unwrap())
+        .collect();
+    assert_eq!(Some(139_629_729), results[4].last_output);
+}
+
+#[test]
+fn test_input_timeout_and_errors() {
+    let (sender, input) = mpsc::channel();
+    let (output, _receiver) = mpsc::channel();
+    let handle = spawn(
+        Machine::new(vec![3, 0, 99]),
+        input,
+        output,
+        Some(Duration::from_millis(10)),
+    );
+    assert_eq!(
+        Err(ThreadError::InputTimeout { ip: 0 }),
+        handle.join().unwrap().map(|finished| finished.exit)
+    );
+    drop(sender);
+
+    let (_sender, input) = mpsc::channel();
+    let (output, _receiver) = mpsc::channel();
+    let handle = spawn(Machine::new(vec![42]), input, output, None);
+    assert_eq!(
+        Err(ThreadError::Machine(MachineError::UnknownOpcode {
+            ip: 0,
+            value: 42
+        })),
+        handle.join().unwrap().map(|finished| finished.exit)
+    );
+}